@@ -1,7 +1,15 @@
 #!/usr/bin/env node
 /* eslint-disable no-undef */
 import { execSync } from "child_process";
-import { chmodSync, existsSync } from "fs";
+import { createHash } from "crypto";
+import {
+  chmodSync,
+  existsSync,
+  readdirSync,
+  readFileSync,
+  statSync,
+  writeFileSync,
+} from "fs";
 import { resolve } from "path";
 import { fileURLToPath } from "url";
 import { dirname } from "path";
@@ -10,6 +18,8 @@ const __filename = fileURLToPath(import.meta.url);
 const __dirname = dirname(__filename);
 const packageRoot = resolve(__dirname, "..", "");
 const bundleFile = resolve(packageRoot, "dist/wc-language-server.bundle.cjs");
+const binDir = resolve(packageRoot, "bin");
+const checksumsFile = resolve(binDir, "SHA256SUMS");
 
 // Define targets to build for
 const targets = [
@@ -49,7 +59,7 @@ async function run() {
   // Build for each target
   for (const { target, suffix } of targets) {
     const executableName = `wc-language-server-${suffix}${suffix.includes("windows") ? ".exe" : ""}`;
-    const outFile = resolve(packageRoot, "bin", executableName);
+    const outFile = resolve(binDir, executableName);
 
     console.log(
       `[language-server] Compiling bundle to executable for ${suffix}...`,
@@ -82,6 +92,28 @@ async function run() {
   }
 
   console.log("[language-server] All executables built successfully");
+
+  writeChecksums();
+}
+
+/**
+ * Writes a `sha256sum`-compatible SHA256SUMS file covering every release asset in `bin/`.
+ * Editor extensions use it to verify downloads before launching them.
+ */
+function writeChecksums() {
+  const entries = readdirSync(binDir)
+    .filter((entry) => entry !== "SHA256SUMS" && !entry.startsWith("."))
+    .filter((entry) => statSync(resolve(binDir, entry)).isFile())
+    .sort()
+    .map((entry) => {
+      const digest = createHash("sha256")
+        .update(readFileSync(resolve(binDir, entry)))
+        .digest("hex");
+      return `${digest}  ${entry}`;
+    });
+
+  writeFileSync(checksumsFile, `${entries.join("\n")}\n`);
+  console.log("[language-server] Wrote release checksums:", checksumsFile);
 }
 
 run().catch((error) => {
@@ -1,7 +1,7 @@
 #!/usr/bin/env node
 /* eslint-disable no-undef */
 import { build } from "esbuild";
-import { chmodSync, mkdirSync, copyFileSync } from "fs";
+import { chmodSync, mkdirSync, copyFileSync, readFileSync } from "fs";
 import { createRequire } from "module";
 import { dirname, resolve } from "path";
 import { fileURLToPath } from "url";
@@ -15,6 +15,9 @@ const outfile = resolve(outDir, "wc-language-server.bundle.cjs");
 const binDir = resolve(packageRoot, "bin");
 const binfile = resolve(binDir, "wc-language-server.js");
 const require = createRequire(import.meta.url);
+const { version } = JSON.parse(
+  readFileSync(resolve(packageRoot, "package.json"), "utf-8"),
+);
 
 const umdToEsmPlugin = {
   name: "umd2esm",
@@ -49,6 +52,10 @@ async function run() {
       js: "#!/usr/bin/env node\n// Bundled by esbuild for the Web Components Language Server\n",
     },
     minify: true,
+    // Bundles and compiled executables can't read package.json at runtime, so `--version` uses this.
+    define: {
+      __WC_LANGUAGE_SERVER_VERSION__: JSON.stringify(version),
+    },
     logLevel: "info",
     plugins: [umdToEsmPlugin],
   });
@@ -8,13 +8,26 @@ import { create as createEmmetService } from "volar-service-emmet";
 import { create as createCssService } from "volar-service-css";
 import { create as createHtmlService } from "volar-service-html";
 import { manifestService } from "./services/manifest-service.js";
+import {
+  configurationService,
+  readClientConfig,
+  readWorkspaceLocations,
+} from "./services/configuration-service.js";
 import { webComponentPlugin } from "./plugins/web-component-plugin.js";
-import { readFileSync } from "fs";
+import { readFileSync, writeFileSync } from "fs";
 import { join, dirname } from "path";
 import { fileURLToPath } from "url";
 
+/** Package version inlined by the single-file bundle build; undefined when running from `dist`. */
+declare const __WC_LANGUAGE_SERVER_VERSION__: string | undefined;
+
 // Handle --version argument
 if (process.argv.includes("--version")) {
+  if (typeof __WC_LANGUAGE_SERVER_VERSION__ !== "undefined") {
+    console.log(__WC_LANGUAGE_SERVER_VERSION__);
+    process.exit(0);
+  }
+
   try {
     const __filename = fileURLToPath(import.meta.url);
     const __dirname = dirname(__filename);
@@ -55,6 +68,21 @@ connection.listen();
  */
 connection.onInitialize((params: InitializeParams) => {
   try {
+    // Monorepo configs and manifests the editor found in workspace packages, and options
+    // it resolved from its own settings.
+    const locations = readWorkspaceLocations(params.initializationOptions);
+    const clientConfig = readClientConfig(params.initializationOptions);
+    if (
+      locations.configPaths.length ||
+      locations.manifestPaths.length ||
+      Object.keys(clientConfig).length
+    ) {
+      configurationService.setWorkspaceLocations(locations);
+      configurationService.setClientConfig(clientConfig);
+      void configurationService
+        .loadConfig()
+        .then(() => manifestService.reload());
+    }
     return server.initialize(params, createSimpleProject([]), [
       // Order matters: base services first, then our custom plugin
       // This ensures HTML/CSS/Emmet completions are available first
@@ -89,7 +117,27 @@ connection.onRequest("wctools/getDocs", () => {
  * Handles the LSP initialized notification from the client.
  * Called after the server has been successfully initialized.
  */
-connection.onInitialized(server.initialized);
+connection.onInitialized(() => {
+  server.initialized();
+  markStarted();
+});
+
+/**
+ * Lets the editor extension know this server version starts successfully by writing the
+ * marker file named in `WC_LANGUAGE_SERVER_STARTED_MARKER`, if any.
+ */
+function markStarted() {
+  const marker = process.env.WC_LANGUAGE_SERVER_STARTED_MARKER;
+  if (!marker) {
+    return;
+  }
+
+  try {
+    writeFileSync(marker, new Date().toISOString());
+  } catch (error) {
+    console.error("Failed to write started marker:", error);
+  }
+}
 
 /**
  * Handles the LSP shutdown request from the client.
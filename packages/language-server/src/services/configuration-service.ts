@@ -2,7 +2,7 @@ import * as path from "path";
 import * as fs from "fs";
 import {
   BaseConfigurationManager,
-  DEFAULT_CONFIG,
+  findConfigFile,
   loadConfig as loadConfigFileOrDir,
   WCConfig,
 } from "./shared-configuration.js";
@@ -15,18 +15,156 @@ export type {
   DiagnosticSeverityOptions,
 } from "./shared-configuration.js";
 
+/**
+ * Monorepo locations an editor can send in `initializationOptions`: the workspace packages
+ * that have their own `wc.config.*` or custom-elements manifest, as absolute paths.
+ */
+export interface WorkspaceLocations {
+  roots: string[];
+  configPaths: string[];
+  manifestPaths: string[];
+}
+
+/**
+ * Reads `roots`, `configPaths` and `manifestPaths` from the client's `initializationOptions`,
+ * ignoring anything that is not a list of strings.
+ */
+export function readWorkspaceLocations(options: unknown): WorkspaceLocations {
+  const record =
+    options && typeof options === "object"
+      ? (options as Record<string, unknown>)
+      : {};
+  const strings = (value: unknown): string[] =>
+    Array.isArray(value)
+      ? value.filter((item): item is string => typeof item === "string")
+      : [];
+  return {
+    roots: strings(record.roots),
+    configPaths: strings(record.configPaths),
+    manifestPaths: strings(record.manifestPaths),
+  };
+}
+
+/** The `WCConfig` options an editor can send in `initializationOptions`. */
+const CLIENT_CONFIG_KEYS = [
+  "manifestSrc",
+  "typeSrc",
+  "diagnosticSeverity",
+  "globalModulePath",
+  "include",
+  "exclude",
+  "debug",
+  "libraries",
+] as const;
+
+/**
+ * Reads the server options an editor resolved from its own settings (Zed layers
+ * `wc.config.json` and its `lsp.wc-language-server.settings`) from the client's
+ * `initializationOptions`, ignoring every other key.
+ */
+export function readClientConfig(options: unknown): Partial<WCConfig> {
+  if (!options || typeof options !== "object") {
+    return {};
+  }
+  const record = options as Record<string, unknown>;
+  const config: Record<string, unknown> = {};
+  for (const key of CLIENT_CONFIG_KEYS) {
+    if (record[key] !== undefined) {
+      config[key] = record[key];
+    }
+  }
+  return config as Partial<WCConfig>;
+}
+
+/**
+ * Applies the editor's options on top of a config file's. `diagnosticSeverity` entries and
+ * `libraries` merge by name; any other option the editor sets replaces the file's.
+ */
+export function layerConfig(
+  fileConfig: Partial<WCConfig>,
+  clientConfig: Partial<WCConfig>,
+): Partial<WCConfig> {
+  const layered: Partial<WCConfig> = {
+    ...fileConfig,
+    ...clientConfig,
+    diagnosticSeverity: {
+      ...fileConfig.diagnosticSeverity,
+      ...clientConfig.diagnosticSeverity,
+    },
+  };
+  if (fileConfig.libraries || clientConfig.libraries) {
+    const libraries = { ...fileConfig.libraries };
+    for (const [name, library] of Object.entries(
+      clientConfig.libraries ?? {},
+    )) {
+      libraries[name] = {
+        ...libraries[name],
+        ...library,
+        diagnosticSeverity: {
+          ...libraries[name]?.diagnosticSeverity,
+          ...library.diagnosticSeverity,
+        },
+      };
+    }
+    layered.libraries = libraries;
+  }
+  return layered;
+}
+
+/**
+ * The config file to load: one in the workspace root, or else the first workspace package
+ * config that exists.
+ */
+export function resolveConfigPath(
+  workspaceRoot: string,
+  locations: WorkspaceLocations,
+): string | undefined {
+  return (
+    findConfigFile(workspaceRoot) ??
+    locations.configPaths.find((configPath) => fs.existsSync(configPath))
+  );
+}
+
 export class ConfigurationService extends BaseConfigurationManager {
   private workspaceRoot: string = process.cwd();
+  private workspaceLocations: WorkspaceLocations = {
+    roots: [],
+    configPaths: [],
+    manifestPaths: [],
+  };
+  private clientConfig: Partial<WCConfig> = {};
 
   constructor() {
     super();
     void this.loadConfig();
   }
 
+  /** Manifests found in workspace packages, loaded alongside the configured ones. */
+  public get workspaceManifestPaths(): string[] {
+    return this.workspaceLocations.manifestPaths;
+  }
+
+  /** Uses the monorepo locations the client sent; call `loadConfig` afterwards to apply them. */
+  public setWorkspaceLocations(locations: WorkspaceLocations): void {
+    debug("config:workspaceLocations", locations);
+    this.workspaceLocations = locations;
+  }
+
+  /**
+   * Uses the options the client sent on top of any config file; call `loadConfig` afterwards
+   * to apply them.
+   */
+  public setClientConfig(config: Partial<WCConfig>): void {
+    debug("config:clientConfig", Object.keys(config));
+    this.clientConfig = this.validateConfig(config);
+  }
+
   public async loadConfig(): Promise<void> {
     try {
       debug("config:load:start", { workspaceRoot: this.workspaceRoot });
-      const configPath = path.join(this.workspaceRoot, "wc.config.js");
+      const configPath =
+        resolveConfigPath(this.workspaceRoot, this.workspaceLocations) ??
+        path.join(this.workspaceRoot, "wc.config.js");
       // If an explicit config file exists at configPath, load that file directly.
       if (fs.existsSync(configPath)) {
         debug("config:load:explicitFound", { path: configPath });
@@ -36,7 +174,21 @@ export class ConfigurationService extends BaseConfigurationManager {
         );
 
         const validated = this.validateConfig(userConfig || {});
-        this.config = this.mergeWithDefaults(validated as WCConfig);
+        // A workspace package's config points at its manifest relative to the package.
+        const configDir = path.dirname(configPath);
+        if (
+          configDir !== this.workspaceRoot &&
+          validated.manifestSrc &&
+          !/^[a-z]+:\/\//i.test(validated.manifestSrc)
+        ) {
+          validated.manifestSrc = path.resolve(
+            configDir,
+            validated.manifestSrc,
+          );
+        }
+        this.config = this.mergeWithDefaults(
+          layerConfig(validated, this.clientConfig),
+        );
         debug("config:load:explicitMerged", {
           debug: this.config.debug,
           include: this.config.include,
@@ -51,7 +203,10 @@ export class ConfigurationService extends BaseConfigurationManager {
           this.workspaceRoot,
         )) as Partial<WCConfig> | undefined;
         this.config = this.mergeWithDefaults(
-          this.validateConfig(userConfig || {}),
+          layerConfig(
+            this.validateConfig(userConfig || {}),
+            this.clientConfig,
+          ),
         );
         debug("config:load:searchMerged", {
           debug: this.config.debug,
@@ -62,7 +217,7 @@ export class ConfigurationService extends BaseConfigurationManager {
       }
     } catch (e) {
       warn("Failed to load config, using default:", e);
-      this.config = DEFAULT_CONFIG;
+      this.config = this.mergeWithDefaults(this.clientConfig);
       debug("config:load:defaultApplied");
     }
 
@@ -216,6 +216,11 @@ export class ManifestService {
       debug("cem:config:primary:none");
     }
 
+    for (const manifestPath of configurationService.workspaceManifestPaths) {
+      debug("cem:config:workspace", { manifestPath });
+      this.loadManifestFromFile(path.dirname(manifestPath), manifestPath);
+    }
+
     const libraryConfigs = configurationService.config.libraries;
     if (!libraryConfigs) {
       return;
@@ -132,6 +132,7 @@ export const CONFIG_FILE_NAMES = [
   "wc.config.js",
   "wc.config.ts",
   "wc.config.mjs",
+  "wc.config.json",
 ];
 
 /**
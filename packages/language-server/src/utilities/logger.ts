@@ -8,7 +8,9 @@ export function setEnableDebugging(enableDebugging: boolean) {
 const PREFIX = "[wctools]";
 
 export function debug(...args: unknown[]) {
-  if (isDebuggingEnabled) {
+  // Logging is on when either `WC_DEBUG` (set by editor debug launch modes) or the
+  // `debug` config option enables it; neither can turn the other off.
+  if (DEBUG || isDebuggingEnabled) {
     // Use console.debug where available
     console.debug("[debug]", ...args);
   }
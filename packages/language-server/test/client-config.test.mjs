@@ -0,0 +1,58 @@
+import test from "node:test";
+import assert from "node:assert/strict";
+
+import {
+  layerConfig,
+  readClientConfig,
+} from "../dist/services/configuration-service.js";
+import { CONFIG_FILE_NAMES } from "../dist/services/shared-configuration.js";
+
+test("readClientConfig keeps only server options", () => {
+  assert.deepEqual(
+    readClientConfig({
+      manifestSrc: "/repo/custom-elements.json",
+      diagnosticSeverity: { unknownElement: "warning" },
+      roots: ["/repo/packages/ui"],
+      html: { format: { enable: false } },
+    }),
+    {
+      manifestSrc: "/repo/custom-elements.json",
+      diagnosticSeverity: { unknownElement: "warning" },
+    },
+  );
+  assert.deepEqual(readClientConfig(undefined), {});
+});
+
+test("layerConfig lets the client override the config file by name", () => {
+  const layered = layerConfig(
+    {
+      manifestSrc: "./custom-elements.json",
+      exclude: ["dist/**"],
+      diagnosticSeverity: { unknownElement: "off", unknownAttribute: "off" },
+      libraries: { "@acme/ui": { typeSrc: "expandedType" } },
+    },
+    {
+      manifestSrc: "/repo/cem.json",
+      diagnosticSeverity: { unknownElement: "warning" },
+      libraries: { "@acme/ui": { manifestSrc: "/repo/acme.json" } },
+    },
+  );
+
+  assert.equal(layered.manifestSrc, "/repo/cem.json");
+  assert.deepEqual(layered.exclude, ["dist/**"]);
+  assert.deepEqual(layered.diagnosticSeverity, {
+    unknownElement: "warning",
+    unknownAttribute: "off",
+  });
+  assert.deepEqual(layered.libraries, {
+    "@acme/ui": {
+      typeSrc: "expandedType",
+      manifestSrc: "/repo/acme.json",
+      diagnosticSeverity: {},
+    },
+  });
+});
+
+test("wc.config.json is a config file the server loads", () => {
+  assert.ok(CONFIG_FILE_NAMES.includes("wc.config.json"));
+});
@@ -0,0 +1,54 @@
+import fs from "fs";
+import os from "os";
+import path from "path";
+import test from "node:test";
+import assert from "node:assert/strict";
+
+import {
+  readWorkspaceLocations,
+  resolveConfigPath,
+} from "../dist/services/configuration-service.js";
+
+test("readWorkspaceLocations keeps only lists of strings", () => {
+  assert.deepEqual(
+    readWorkspaceLocations({
+      roots: ["/repo/packages/ui", 42],
+      configPaths: "/repo/packages/ui/wc.config.js",
+      manifestPaths: ["/repo/packages/ui/custom-elements.json"],
+      typeSrc: "parsedType",
+    }),
+    {
+      roots: ["/repo/packages/ui"],
+      configPaths: [],
+      manifestPaths: ["/repo/packages/ui/custom-elements.json"],
+    },
+  );
+  assert.deepEqual(readWorkspaceLocations(undefined), {
+    roots: [],
+    configPaths: [],
+    manifestPaths: [],
+  });
+});
+
+test("resolveConfigPath falls back to a workspace package config", () => {
+  const root = fs.mkdtempSync(path.join(os.tmpdir(), "wc-workspace-"));
+  const packageConfig = path.join(root, "packages/ui/wc.config.js");
+  fs.mkdirSync(path.dirname(packageConfig), { recursive: true });
+  fs.writeFileSync(packageConfig, "export default {};");
+  const locations = readWorkspaceLocations({
+    configPaths: [
+      path.join(root, "packages/missing/wc.config.js"),
+      packageConfig,
+    ],
+  });
+
+  try {
+    assert.equal(resolveConfigPath(root, locations), packageConfig);
+
+    const rootConfig = path.join(root, "wc.config.js");
+    fs.writeFileSync(rootConfig, "export default {};");
+    assert.equal(resolveConfigPath(root, locations), rootConfig);
+  } finally {
+    fs.rmSync(root, { recursive: true, force: true });
+  }
+});
@@ -8,6 +8,7 @@ import {
   rmSync,
   cpSync,
   readdirSync,
+  readFileSync,
   writeFileSync,
 } from "fs";
 import { dirname, resolve } from "path";
@@ -121,8 +122,17 @@ if (existsSync(tsSource)) {
   );
 }
 
+// The extension reads `version` to know which release it ships, so a newer
+// download can replace the bundled server later.
+const { version } = JSON.parse(
+  readFileSync(
+    resolve(repoRoot, "packages/language-server/package.json"),
+    "utf8",
+  ),
+);
 const serverPackage = {
   name: "@wc-toolkit/zed-language-server-runtime",
+  version,
   type: "commonjs",
 };
 
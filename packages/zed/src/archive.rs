@@ -0,0 +1,163 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+use zed_extension_api as zed;
+
+/// How a release asset is packaged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AssetFormat {
+    GzipTar,
+    Zip,
+    Gzip,
+    Uncompressed,
+}
+
+impl AssetFormat {
+    /// Formats in order of preference; compressed variants win over the raw executable.
+    const PREFERENCE: [Self; 4] = [Self::GzipTar, Self::Zip, Self::Gzip, Self::Uncompressed];
+
+    fn extension(self) -> &'static str {
+        match self {
+            Self::GzipTar => ".tar.gz",
+            Self::Zip => ".zip",
+            Self::Gzip => ".gz",
+            Self::Uncompressed => "",
+        }
+    }
+
+    pub fn file_type(self) -> zed::DownloadedFileType {
+        match self {
+            Self::GzipTar => zed::DownloadedFileType::GzipTar,
+            Self::Zip => zed::DownloadedFileType::Zip,
+            Self::Gzip => zed::DownloadedFileType::Gzip,
+            Self::Uncompressed => zed::DownloadedFileType::Uncompressed,
+        }
+    }
+
+    /// Whether the asset is unpacked into a versioned directory rather than written in place.
+    pub fn is_compressed(self) -> bool {
+        self != Self::Uncompressed
+    }
+}
+
+/// Release asset names that can provide `executable`, most preferred first.
+///
+/// Archives drop the `.exe` suffix (`wc-language-server-windows-x64.zip`), while a
+/// gzipped single file keeps it (`wc-language-server-windows-x64.exe.gz`).
+pub fn asset_candidates(executable: &str) -> Vec<(String, AssetFormat)> {
+    let stem = executable.strip_suffix(".exe").unwrap_or(executable);
+    AssetFormat::PREFERENCE
+        .into_iter()
+        .map(|format| {
+            let base = match format {
+                AssetFormat::GzipTar | AssetFormat::Zip => stem,
+                AssetFormat::Gzip | AssetFormat::Uncompressed => executable,
+            };
+            (format!("{base}{}", format.extension()), format)
+        })
+        .collect()
+}
+
+/// Prefix of every release's install directory.
+pub const VERSION_DIR_PREFIX: &str = "release-";
+
+/// Directory name, safe on every platform, for the installed server of a release.
+pub fn version_dir_name(version: &str) -> String {
+    let sanitized: String = version
+        .trim_start_matches('@')
+        .chars()
+        .map(|ch| {
+            if ch.is_ascii_alphanumeric() || matches!(ch, '.' | '-' | '_') {
+                ch
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    format!("{VERSION_DIR_PREFIX}{sanitized}")
+}
+
+/// Finds the first file under `dir` whose name is one of `names`, searching breadth-first
+/// so a top-level executable wins over copies nested in the archive.
+pub fn find_executable(dir: &Path, names: &[&str]) -> Option<PathBuf> {
+    let mut pending = vec![dir.to_path_buf()];
+    while !pending.is_empty() {
+        let mut next = Vec::new();
+        for current in pending {
+            let Ok(entries) = fs::read_dir(&current) else {
+                continue;
+            };
+            let mut entries: Vec<_> = entries.flatten().map(|entry| entry.path()).collect();
+            entries.sort();
+            for path in entries {
+                if path.is_dir() {
+                    next.push(path);
+                } else if path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| names.contains(&name))
+                {
+                    return Some(path);
+                }
+            }
+        }
+        pending = next;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    #[test]
+    fn asset_candidates_prefer_compressed_variants() {
+        let names: Vec<_> = asset_candidates("wc-language-server-windows-x64.exe")
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+
+        assert_eq!(
+            names,
+            [
+                "wc-language-server-windows-x64.tar.gz",
+                "wc-language-server-windows-x64.zip",
+                "wc-language-server-windows-x64.exe.gz",
+                "wc-language-server-windows-x64.exe",
+            ]
+        );
+        assert_eq!(
+            asset_candidates("wc-language-server-linux-x64")[0],
+            (
+                "wc-language-server-linux-x64.tar.gz".to_string(),
+                AssetFormat::GzipTar
+            )
+        );
+    }
+
+    #[test]
+    fn version_dir_name_is_path_safe() {
+        assert_eq!(
+            version_dir_name("@wc-toolkit/language-server@0.0.7"),
+            "release-wc-toolkit-language-server-0.0.7"
+        );
+        assert_eq!(version_dir_name("v1.2.3-rc.1"), "release-v1.2.3-rc.1");
+    }
+
+    #[test]
+    fn find_executable_searches_extracted_tree() {
+        let root = TempDir::new("archive");
+        let nested = root.join("package/bin");
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(root.join("README.md"), "").unwrap();
+        fs::write(nested.join("wc-language-server"), "").unwrap();
+
+        assert_eq!(
+            find_executable(&root, &["wc-language-server"]),
+            Some(nested.join("wc-language-server"))
+        );
+        assert_eq!(find_executable(&root, &["missing"]), None);
+    }
+}
@@ -0,0 +1,94 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+use zed_extension_api::serde_json::{self, Value};
+
+use crate::source;
+
+/// Directory, relative to the extension root, that `scripts/bundle-language-server.mjs`
+/// copies the server into.
+const BUNDLE_DIR: &str = "server";
+
+/// The language server shipped in the extension's `server/` assets.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BundledServer {
+    /// Release tag of the bundled build.
+    pub version: String,
+    /// Directory holding the JS bundle and any native executables.
+    pub bin_dir: PathBuf,
+}
+
+impl BundledServer {
+    /// Reads the bundle's `package.json`. A bundle without a `version` is ignored, since
+    /// nothing could tell when a download is newer.
+    pub fn find(extension_root: &Path) -> Option<Self> {
+        let dir = extension_root.join(BUNDLE_DIR);
+        let contents = fs::read_to_string(dir.join("package.json")).ok()?;
+        let metadata: Value = serde_json::from_str(&contents).ok()?;
+        let version = metadata.get("version")?.as_str()?;
+        Some(Self {
+            version: source::release_tag(version),
+            bin_dir: dir.join("bin"),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{settings::ReleaseChannel, test_support::TempDir, versions::VersionStore};
+
+    #[test]
+    fn find_reads_version_from_bundle_metadata() {
+        let root = TempDir::new("bundled");
+        fs::create_dir_all(root.join(BUNDLE_DIR)).unwrap();
+        let metadata = root.join(BUNDLE_DIR).join("package.json");
+
+        fs::write(
+            &metadata,
+            r#"{ "name": "@wc-toolkit/zed-language-server-runtime", "type": "commonjs" }"#,
+        )
+        .unwrap();
+        assert_eq!(BundledServer::find(&root), None);
+
+        fs::write(
+            &metadata,
+            r#"{ "name": "@wc-toolkit/zed-language-server-runtime", "version": "0.0.9" }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            BundledServer::find(&root),
+            Some(BundledServer {
+                version: "@wc-toolkit/language-server@0.0.9".to_string(),
+                bin_dir: root.join("server/bin"),
+            })
+        );
+    }
+
+    #[test]
+    fn managed_cleanup_leaves_the_bundle_alone() {
+        let root = TempDir::new("bundled-gc");
+        let bin_dir = root.join(BUNDLE_DIR).join("bin");
+        fs::create_dir_all(&bin_dir).unwrap();
+        fs::write(
+            root.join(BUNDLE_DIR).join("package.json"),
+            r#"{ "version": "0.0.9" }"#,
+        )
+        .unwrap();
+        fs::write(bin_dir.join("wc-language-server.js"), "").unwrap();
+        let bundled = BundledServer::find(&root).unwrap();
+
+        for channel in [ReleaseChannel::Stable, ReleaseChannel::Prerelease] {
+            let server_dir = root.join(channel.server_dir());
+            assert!(!server_dir.starts_with(&bundled.bin_dir));
+            fs::create_dir_all(&server_dir).unwrap();
+            fs::write(server_dir.join("wc-language-server.js"), "").unwrap();
+
+            let removed = VersionStore::new(&server_dir)
+                .remove_stale_assets(None, &bundled.bin_dir.join("wc-language-server.js"));
+            assert_eq!(removed, [server_dir.join("wc-language-server.js")]);
+        }
+        assert!(bundled.bin_dir.join("wc-language-server.js").exists());
+    }
+}
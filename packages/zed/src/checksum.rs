@@ -0,0 +1,93 @@
+use sha2::{Digest, Sha256};
+use std::{fs::File, io::Read, path::Path};
+use zed_extension_api::Result;
+
+/// Release asset listing the SHA-256 digest of every other asset, in `sha256sum` format.
+pub const CHECKSUMS_ASSET_NAME: &str = "SHA256SUMS";
+
+/// Looks up the expected digest for `asset_name` in the contents of a `SHA256SUMS` file.
+pub fn expected_checksum(checksums: &str, asset_name: &str) -> Option<String> {
+    checksums.lines().find_map(|line| {
+        let (digest, name) = line.trim().split_once(char::is_whitespace)?;
+        // `sha256sum --binary` prefixes file names with `*`.
+        let name = name.trim_start().trim_start_matches('*');
+        (name == asset_name).then(|| digest.to_ascii_lowercase())
+    })
+}
+
+/// Computes the lowercase hex SHA-256 digest of the file at `path`.
+pub fn sha256_file(path: &Path) -> Result<String> {
+    let mut file = File::open(path)
+        .map_err(|err| format!("failed to open {} for hashing: {err}", path.display()))?;
+    let mut hasher = Sha256::new();
+    let mut buffer = [0u8; 64 * 1024];
+    loop {
+        let read = file
+            .read(&mut buffer)
+            .map_err(|err| format!("failed to read {} for hashing: {err}", path.display()))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect())
+}
+
+/// Fails unless the file at `path` hashes to `expected`.
+pub fn verify_file(path: &Path, expected: &str) -> Result<()> {
+    let actual = sha256_file(path)?;
+    if actual.eq_ignore_ascii_case(expected) {
+        Ok(())
+    } else {
+        Err(format!(
+            "checksum mismatch for {}: expected {expected}, got {actual}",
+            path.display()
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+    use std::fs;
+
+    const HELLO_SHA256: &str = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+
+    #[test]
+    fn expected_checksum_reads_text_and_binary_entries() {
+        let checksums = format!(
+            "{HELLO_SHA256}  wc-language-server.js\n\
+             {}  *wc-language-server-linux-x64\n",
+            HELLO_SHA256.to_ascii_uppercase()
+        );
+
+        assert_eq!(
+            expected_checksum(&checksums, "wc-language-server.js").as_deref(),
+            Some(HELLO_SHA256)
+        );
+        assert_eq!(
+            expected_checksum(&checksums, "wc-language-server-linux-x64").as_deref(),
+            Some(HELLO_SHA256)
+        );
+        assert_eq!(
+            expected_checksum(&checksums, "wc-language-server-macos-x64"),
+            None
+        );
+    }
+
+    #[test]
+    fn verify_file_compares_sha256_digest() {
+        let dir = TempDir::new("checksum");
+        let path = dir.join("hello.txt");
+        fs::write(&path, "hello").unwrap();
+
+        assert_eq!(sha256_file(&path).unwrap(), HELLO_SHA256);
+        assert!(verify_file(&path, HELLO_SHA256).is_ok());
+        assert!(verify_file(&path, &"0".repeat(64)).is_err());
+    }
+}
@@ -0,0 +1,458 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, path::Path};
+use zed_extension_api::serde_json::{self, Map, Value};
+
+use crate::settings::ExtensionSettings;
+
+/// Static config file in the worktree root, layered below Zed settings. The server loads it
+/// too, along with `wc.config.js` and friends, and applies these options on top.
+pub const CONFIG_FILE_NAME: &str = "wc.config.json";
+const SEVERITIES: [&str; 5] = ["error", "warning", "info", "hint", "off"];
+/// Diagnostics whose severity can be configured, as in the server's `DEFAULT_CONFIG`.
+const DIAGNOSTICS: [&str; 8] = [
+    "invalidBoolean",
+    "invalidNumber",
+    "invalidAttributeValue",
+    "deprecatedAttribute",
+    "deprecatedElement",
+    "duplicateAttribute",
+    "unknownElement",
+    "unknownAttribute",
+];
+/// Options a library override accepts.
+const LIBRARY_KEYS: [&str; 4] = [
+    "manifestSrc",
+    "typeSrc",
+    "diagnosticSeverity",
+    "globalModulePath",
+];
+/// Top-level options besides the library ones. `$schema` is allowed for editor completion in
+/// `wc.config.json`.
+const CONFIG_KEYS: [&str; 5] = ["include", "exclude", "debug", "libraries", "$schema"];
+
+/// Options for one library, mirroring `LibraryConfig` in the server's
+/// `shared-configuration.ts` minus its function-valued fields, which JSON cannot express.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct LibraryConfig {
+    /// Local path or URL of the custom-elements manifest.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub manifest_src: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub type_src: Option<String>,
+    /// Severity per diagnostic, e.g. `unknownElement: "warning"`.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub diagnostic_severity: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub global_module_path: Option<String>,
+    /// Options this model does not know, such as the `html`, `css` and `emmet` sections the
+    /// server's built-in services read. They are passed to the server unchanged.
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+/// Language-server options, mirroring `WCConfig` in the server's `shared-configuration.ts`.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct WcConfig {
+    #[serde(flatten)]
+    pub base: LibraryConfig,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub include: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exclude: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub debug: Option<bool>,
+    /// Per-package overrides, keyed by package name.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub libraries: BTreeMap<String, LibraryConfig>,
+}
+
+/// Layers `wc.config.json` (`config_file`, the file's contents) and the server options in
+/// Zed's `lsp.wc-language-server.settings`, later layers winning; the server fills in its own
+/// defaults for anything neither sets. Manifest and module paths are then made absolute: `~`
+/// expands to `home`, and relative paths are resolved against the worktree `root`.
+///
+/// Problems never fail resolution, so they cannot keep the server from starting. They are
+/// returned as warnings instead: an unreadable layer is skipped, an invalid severity is
+/// dropped, and an unknown option is passed through.
+pub fn resolve(
+    root: &Path,
+    home: Option<&str>,
+    config_file: Option<&str>,
+    settings: Option<&Value>,
+) -> (WcConfig, Vec<String>) {
+    let mut config = WcConfig::default();
+    let mut warnings = Vec::new();
+    if let Some(contents) = config_file {
+        match serde_json::from_str(contents) {
+            Ok(value) => {
+                if let Some(layer) = WcConfig::parse(&value, CONFIG_FILE_NAME, &[], &mut warnings) {
+                    config.merge(layer);
+                }
+            }
+            Err(err) => warnings.push(format!(
+                "{CONFIG_FILE_NAME} is not valid JSON, so it is ignored: {err}"
+            )),
+        }
+    }
+    if let Some(settings) = settings
+        && let Some(layer) = WcConfig::parse(
+            settings,
+            "lsp.wc-language-server.settings",
+            &ExtensionSettings::KEYS,
+            &mut warnings,
+        )
+    {
+        config.merge(layer);
+    }
+    config.resolve_paths(root, home);
+    (config, warnings)
+}
+
+impl WcConfig {
+    /// Reads one layer from `value`, or `None` if it does not have the shape of server options.
+    /// `other_keys` are left out (the extension's own settings share the object in Zed
+    /// settings). Any other key that is not a server option is kept, but reported in
+    /// `warnings` in case it is misspelled; so are invalid severities, which are dropped.
+    /// `source` names the layer in warnings.
+    fn parse(
+        value: &Value,
+        source: &str,
+        other_keys: &[&str],
+        warnings: &mut Vec<String>,
+    ) -> Option<Self> {
+        let mut value = value.clone();
+        if let Value::Object(options) = &mut value {
+            options.retain(|key, _| key != "$schema" && !other_keys.contains(&key.as_str()));
+        }
+        let top_level: Vec<&str> = LIBRARY_KEYS.iter().chain(&CONFIG_KEYS).copied().collect();
+        check_keys(&value, "", &top_level, source, warnings);
+        if let Some(Value::Object(libraries)) = value.get("libraries") {
+            for (name, library) in libraries {
+                check_keys(
+                    library,
+                    &format!("libraries.{name}."),
+                    &LIBRARY_KEYS,
+                    source,
+                    warnings,
+                );
+            }
+        }
+        let mut config: Self = match serde_json::from_value(value) {
+            Ok(config) => config,
+            Err(err) => {
+                warnings.push(format!(
+                    "invalid language server options in {source}, so they are ignored: {err}"
+                ));
+                return None;
+            }
+        };
+        validate_severities(
+            &mut config.base.diagnostic_severity,
+            "diagnosticSeverity",
+            source,
+            warnings,
+        );
+        for (name, library) in &mut config.libraries {
+            validate_severities(
+                &mut library.diagnostic_severity,
+                &format!("libraries.{name}.diagnosticSeverity"),
+                source,
+                warnings,
+            );
+        }
+        Some(config)
+    }
+
+    /// Applies `layer` on top of this config. Severities and libraries merge by name; every
+    /// other option is replaced when the layer sets it.
+    fn merge(&mut self, layer: Self) {
+        self.base.merge(layer.base);
+        self.include = layer.include.or(self.include.take());
+        self.exclude = layer.exclude.or(self.exclude.take());
+        self.debug = layer.debug.or(self.debug);
+        for (name, library) in layer.libraries {
+            self.libraries.entry(name).or_default().merge(library);
+        }
+    }
+
+    fn resolve_paths(&mut self, root: &Path, home: Option<&str>) {
+        for library in std::iter::once(&mut self.base).chain(self.libraries.values_mut()) {
+            for path in [&mut library.manifest_src, &mut library.global_module_path]
+                .into_iter()
+                .flatten()
+            {
+                *path = expand_path(path, root, home);
+            }
+        }
+    }
+
+    /// Adds this config's options to `options` (an object), keeping any the user already set
+    /// in `initialization_options`.
+    pub fn merge_into(&self, options: Value) -> Value {
+        let Value::Object(mut options) = options else {
+            return self.to_value();
+        };
+        if let Value::Object(config) = self.to_value() {
+            for (key, value) in config {
+                options.entry(key).or_insert(value);
+            }
+        }
+        Value::Object(options)
+    }
+
+    pub fn to_value(&self) -> Value {
+        serde_json::to_value(self).unwrap_or_default()
+    }
+}
+
+impl LibraryConfig {
+    fn merge(&mut self, layer: Self) {
+        self.manifest_src = layer.manifest_src.or(self.manifest_src.take());
+        self.type_src = layer.type_src.or(self.type_src.take());
+        self.global_module_path = layer.global_module_path.or(self.global_module_path.take());
+        self.diagnostic_severity.extend(layer.diagnostic_severity);
+        self.other.extend(layer.other);
+    }
+}
+
+fn check_keys(
+    value: &Value,
+    prefix: &str,
+    known: &[&str],
+    source: &str,
+    warnings: &mut Vec<String>,
+) {
+    let Value::Object(options) = value else {
+        return;
+    };
+    for key in options.keys().filter(|key| !known.contains(&key.as_str())) {
+        warnings.push(format!(
+            "`{prefix}{key}` in {source} is not a language server option and is passed on as is; \
+             the options are {}",
+            known.join(", ")
+        ));
+    }
+}
+
+/// Drops severities the server would not understand, with a warning naming the fix.
+fn validate_severities(
+    severities: &mut BTreeMap<String, String>,
+    field: &str,
+    source: &str,
+    warnings: &mut Vec<String>,
+) {
+    severities.retain(|name, severity| {
+        if !DIAGNOSTICS.contains(&name.as_str()) {
+            warnings.push(format!(
+                "`{field}.{name}` in {source} is not a diagnostic the server reports, so it is \
+                 ignored; use one of {}",
+                DIAGNOSTICS.join(", ")
+            ));
+            return false;
+        }
+        if !SEVERITIES.contains(&severity.as_str()) {
+            warnings.push(format!(
+                "`{field}.{name}` in {source} is \"{severity}\", so it is ignored; use one of {}",
+                SEVERITIES.join(", ")
+            ));
+            return false;
+        }
+        true
+    });
+}
+
+/// Makes a manifest or module path absolute. URLs are left alone.
+fn expand_path(path: &str, root: &Path, home: Option<&str>) -> String {
+    if path.contains("://") {
+        return path.to_owned();
+    }
+    if let Some(home) = home
+        && (path == "~" || path.starts_with("~/"))
+    {
+        return format!("{}{}", home.trim_end_matches('/'), &path[1..]);
+    }
+    // `Path::is_absolute` follows the host's rules, which in the extension sandbox never
+    // match Windows drive paths.
+    let windows_absolute = path.as_bytes().get(1) == Some(&b':') || path.starts_with("\\\\");
+    if Path::new(path).is_absolute() || windows_absolute {
+        return path.to_owned();
+    }
+    root.join(path.trim_start_matches("./"))
+        .to_string_lossy()
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn resolve_layers_config_file_and_settings() {
+        let (config, warnings) = resolve(
+            Path::new("/repo"),
+            Some("/home/dev"),
+            Some(
+                r#"{
+                    "$schema": "./node_modules/@wc-toolkit/language-server/schema.json",
+                    "manifestSrc": "./dist/custom-elements.json",
+                    "exclude": ["**/node_modules/**"],
+                    "diagnosticSeverity": { "unknownElement": "warning" },
+                    "libraries": { "@acme/ui": { "manifestSrc": "https://cdn.example.com/cem.json", "typeSrc": "expandedType" } }
+                }"#,
+            ),
+            Some(&json!({
+                "version": "0.0.9",
+                "debug": true,
+                "diagnosticSeverity": { "unknownAttribute": "off", "unknownElement": "error" },
+                "libraries": { "@acme/ui": { "manifestSrc": "~/cem/acme.json" } }
+            })),
+        );
+
+        assert!(warnings.is_empty(), "{warnings:?}");
+        assert_eq!(
+            config.base.manifest_src.as_deref(),
+            Some("/repo/dist/custom-elements.json")
+        );
+        assert_eq!(config.base.type_src, None);
+        assert_eq!(config.exclude, Some(vec!["**/node_modules/**".to_string()]));
+        assert_eq!(config.debug, Some(true));
+        let severity = &config.base.diagnostic_severity;
+        assert_eq!(severity["unknownElement"], "error");
+        assert_eq!(severity["unknownAttribute"], "off");
+        assert_eq!(severity.len(), 2);
+
+        let library = &config.libraries["@acme/ui"];
+        assert_eq!(
+            library.manifest_src.as_deref(),
+            Some("/home/dev/cem/acme.json")
+        );
+        assert_eq!(library.type_src.as_deref(), Some("expandedType"));
+
+        let value = config.to_value();
+        assert_eq!(value["debug"], true);
+        assert!(value.get("version").is_none());
+        assert!(value.get("$schema").is_none());
+    }
+
+    #[test]
+    fn resolve_drops_invalid_severities_with_the_fix() {
+        let (config, warnings) = resolve(
+            Path::new("/repo"),
+            None,
+            Some(
+                r#"{ "libraries": { "lit": { "diagnosticSeverity": { "unknownElment": "off" } } } }"#,
+            ),
+            Some(&json!({
+                "diagnosticSeverity": { "unknownElement": "warn", "unknownAttribute": "off" }
+            })),
+        );
+
+        assert_eq!(
+            config.base.diagnostic_severity,
+            BTreeMap::from([("unknownAttribute".to_string(), "off".to_string())])
+        );
+        assert!(config.libraries["lit"].diagnostic_severity.is_empty());
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings[0].starts_with(
+            "`libraries.lit.diagnosticSeverity.unknownElment` in wc.config.json is not a diagnostic the server reports"
+        ));
+        assert_eq!(
+            warnings[1],
+            "`diagnosticSeverity.unknownElement` in lsp.wc-language-server.settings is \"warn\", so it is ignored; use one of error, warning, info, hint, off"
+        );
+    }
+
+    #[test]
+    fn resolve_skips_layers_it_cannot_read() {
+        let (config, warnings) = resolve(
+            Path::new("/repo"),
+            None,
+            Some("{ manifestSrc: 1 }"),
+            Some(&json!({ "debug": "yes" })),
+        );
+
+        assert_eq!(config, WcConfig::default());
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings[0].starts_with("wc.config.json is not valid JSON, so it is ignored"));
+        assert!(warnings[1].starts_with(
+            "invalid language server options in lsp.wc-language-server.settings, so they are ignored"
+        ));
+    }
+
+    #[test]
+    fn resolve_passes_unknown_options_through_with_a_warning() {
+        let (config, warnings) = resolve(
+            Path::new("/repo"),
+            None,
+            Some(r#"{ "manifestScr": "custom-elements.json" }"#),
+            Some(&json!({
+                "html": { "format": { "enable": false } },
+                "libraries": { "lit": { "exlude": ["**/dist/**"] } }
+            })),
+        );
+
+        let value = config.to_value();
+        assert_eq!(value["manifestScr"], "custom-elements.json");
+        assert_eq!(value["html"], json!({ "format": { "enable": false } }));
+        assert_eq!(value["libraries"]["lit"]["exlude"], json!(["**/dist/**"]));
+        assert_eq!(warnings.len(), 3);
+        assert!(warnings[0].starts_with(
+            "`manifestScr` in wc.config.json is not a language server option and is passed on as is; the options are manifestSrc,"
+        ));
+        assert!(warnings[1].starts_with("`html` in lsp.wc-language-server.settings"));
+        assert!(
+            warnings[2].starts_with("`libraries.lit.exlude` in lsp.wc-language-server.settings")
+        );
+
+        // The extension's own settings share the object in Zed settings, but not the file.
+        let settings = json!({ "channel": "prerelease", "node": { "path": "/usr/bin/node" } });
+        let (config, warnings) = resolve(Path::new("/repo"), None, None, Some(&settings));
+        assert_eq!((config, warnings), (WcConfig::default(), Vec::new()));
+        let (config, warnings) =
+            resolve(Path::new("/repo"), None, Some(&settings.to_string()), None);
+        assert_eq!(config.to_value(), settings);
+        assert!(
+            warnings[0].starts_with("`channel` in wc.config.json is not a language server option")
+        );
+    }
+
+    #[test]
+    fn expand_path_handles_home_urls_and_absolute_paths() {
+        let root = Path::new("/repo");
+        assert_eq!(
+            expand_path("~/cem.json", root, Some("/home/dev/")),
+            "/home/dev/cem.json"
+        );
+        assert_eq!(expand_path("~/cem.json", root, None), "/repo/~/cem.json");
+        assert_eq!(
+            expand_path("https://cdn.example.com/cem.json", root, None),
+            "https://cdn.example.com/cem.json"
+        );
+        assert_eq!(expand_path("/abs/cem.json", root, None), "/abs/cem.json");
+        assert_eq!(
+            expand_path("C:\\design\\cem.json", root, None),
+            "C:\\design\\cem.json"
+        );
+        assert_eq!(expand_path("cem.json", root, None), "/repo/cem.json");
+    }
+
+    #[test]
+    fn merge_into_keeps_user_initialization_options() {
+        let config = WcConfig {
+            base: LibraryConfig {
+                type_src: Some("parsedType".to_string()),
+                manifest_src: Some("/repo/custom-elements.json".to_string()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let merged = config.merge_into(json!({ "typeSrc": "custom", "roots": ["/repo"] }));
+
+        assert_eq!(merged["typeSrc"], "custom");
+        assert_eq!(merged["roots"], json!(["/repo"]));
+        assert_eq!(merged["manifestSrc"], "/repo/custom-elements.json");
+    }
+}
@@ -0,0 +1,170 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, SystemTime},
+};
+use zed_extension_api::Result;
+
+/// Lock file guarding server installs, created inside the server directory.
+pub const INSTALL_LOCK_NAME: &str = ".install.lock";
+
+/// A lock older than this belongs to an install that crashed or was killed.
+const STALE_LOCK_AGE: Duration = Duration::from_secs(10 * 60);
+const LOCK_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Exclusive install lock, released when dropped.
+#[derive(Debug)]
+pub struct InstallLock {
+    path: PathBuf,
+}
+
+impl InstallLock {
+    /// Takes the lock at `path`, waiting up to `timeout` for another Zed window to finish.
+    pub fn acquire(path: &Path, timeout: Duration) -> Result<Self> {
+        let started = SystemTime::now();
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(path) {
+                Ok(mut file) => {
+                    let _ = writeln!(file, "{}", std::process::id());
+                    return Ok(Self {
+                        path: path.to_path_buf(),
+                    });
+                }
+                Err(err) if err.kind() == ErrorKind::AlreadyExists => {
+                    if Self::is_stale(path) {
+                        println!(
+                            "[wc-tools] Removing stale install lock at {}",
+                            path.display()
+                        );
+                        let _ = fs::remove_file(path);
+                        continue;
+                    }
+                    if started.elapsed().unwrap_or_default() >= timeout {
+                        return Err(format!(
+                            "another language server install holds {}",
+                            path.display()
+                        ));
+                    }
+                    thread::sleep(LOCK_POLL_INTERVAL);
+                }
+                Err(err) => {
+                    return Err(format!(
+                        "failed to create install lock {}: {err}",
+                        path.display()
+                    ));
+                }
+            }
+        }
+    }
+
+    fn is_stale(path: &Path) -> bool {
+        fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .is_some_and(|age| age >= STALE_LOCK_AGE)
+    }
+}
+
+impl Drop for InstallLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Runs `operation` up to `attempts` times, doubling the delay after each failure.
+pub fn with_retries<T>(
+    attempts: u32,
+    initial_delay: Duration,
+    mut operation: impl FnMut() -> Result<T>,
+) -> Result<T> {
+    let mut delay = initial_delay;
+    let mut attempt = 1;
+    loop {
+        match operation() {
+            Ok(value) => return Ok(value),
+            Err(err) if attempt >= attempts => return Err(err),
+            Err(err) => {
+                println!(
+                    "[wc-tools] Attempt {attempt}/{attempts} failed: {err}. Retrying in {}ms",
+                    delay.as_millis()
+                );
+                thread::sleep(delay);
+                delay *= 2;
+                attempt += 1;
+            }
+        }
+    }
+}
+
+/// Writes `contents` to a sibling temporary file and renames it over `path`, so readers
+/// never observe a partially written file.
+pub fn write_atomically(path: &Path, contents: &str) -> Result<()> {
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let temp_path = path.with_file_name(format!("{file_name}.tmp"));
+    fs::write(&temp_path, contents)
+        .and_then(|_| fs::rename(&temp_path, path))
+        .map_err(|err| {
+            let _ = fs::remove_file(&temp_path);
+            format!("failed to write {}: {err}", path.display())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+    use std::cell::Cell;
+
+    #[test]
+    fn install_lock_is_exclusive_until_dropped() {
+        let dir = TempDir::new("install-lock");
+        let lock_path = dir.join(INSTALL_LOCK_NAME);
+
+        let lock = InstallLock::acquire(&lock_path, Duration::ZERO).unwrap();
+        assert!(InstallLock::acquire(&lock_path, Duration::ZERO).is_err());
+
+        drop(lock);
+        assert!(!lock_path.exists());
+        assert!(InstallLock::acquire(&lock_path, Duration::ZERO).is_ok());
+    }
+
+    #[test]
+    fn with_retries_stops_after_success_or_last_attempt() {
+        let calls = Cell::new(0);
+        let result = with_retries(3, Duration::ZERO, || {
+            calls.set(calls.get() + 1);
+            if calls.get() < 2 {
+                Err("transient".to_string())
+            } else {
+                Ok(calls.get())
+            }
+        });
+        assert_eq!(result, Ok(2));
+
+        calls.set(0);
+        let result: Result<()> = with_retries(3, Duration::ZERO, || {
+            calls.set(calls.get() + 1);
+            Err("offline".to_string())
+        });
+        assert_eq!(result, Err("offline".to_string()));
+        assert_eq!(calls.get(), 3);
+    }
+
+    #[test]
+    fn write_atomically_replaces_contents() {
+        let dir = TempDir::new("install-atomic");
+        let marker = dir.join(".release-version");
+        fs::write(&marker, "old").unwrap();
+
+        write_atomically(&marker, "new").unwrap();
+
+        assert_eq!(fs::read_to_string(&marker).unwrap(), "new");
+        assert!(!dir.join(".release-version.tmp").exists());
+    }
+}
@@ -1,24 +1,132 @@
-use std::{env, fs, path::PathBuf};
-use zed::settings::LspSettings;
+mod archive;
+mod bundled;
+mod checksum;
+mod config;
+mod install;
+mod manifest;
+mod node;
+mod project;
+mod semver;
+mod settings;
+mod source;
+#[cfg(test)]
+mod test_support;
+mod versions;
+mod workspace;
+
+use archive::AssetFormat;
+use bundled::BundledServer;
+use install::InstallLock;
+use manifest::{InstallManifest, RuntimeKind};
+use settings::{ExtensionSettings, LaunchMode, ReleaseChannel, RuntimeMode};
+use source::{ReleaseSelection, ReleaseSource};
+use std::{
+    collections::{BTreeMap, HashMap},
+    env, fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use versions::VersionStore;
+use zed::settings::{CommandSettings, LspSettings};
 use zed_extension_api::{self as zed, LanguageServerId, Result};
 
 const GITHUB_REPO: &str = "wc-toolkit/wc-language-server";
+/// npm package name of the server, also used as the prefix of its release tags.
+const SERVER_PACKAGE_NAME: &str = "@wc-toolkit/language-server";
 const JS_ASSET_NAME: &str = "wc-language-server.js";
-const SERVER_VERSION_MARKER: &str = "server/bin/.release-version";
+const LAST_UPDATE_CHECK_MARKER: &str = ".last-update-check";
+/// Server releases this extension is known to work with; newer majors are never auto-installed.
+const COMPATIBLE_SERVER_VERSIONS: semver::VersionRange =
+    semver::VersionRange::new((0, 0, 7), (1, 0, 0));
+/// First server release built with a `SHA256SUMS` asset and an inlined `--version`. Earlier
+/// releases are installed without a checksum, since there is nothing to verify them against.
+const FIRST_VERIFIED_RELEASE: (u64, u64, u64) = (0, 0, 8);
+/// What `--version` prints in releases built before the bundle inlined its package version
+/// (those before `FIRST_VERIFIED_RELEASE`).
+const UNKNOWN_VERSION_OUTPUT: &str = "0.0.2";
 const CUSTOM_SERVER_ENV: &str = "WC_LANGUAGE_SERVER_BINARY";
+/// Turns on the language server's debug logging.
+const SERVER_DEBUG_ENV: &str = "WC_DEBUG";
+const INSTALL_LOCK_TIMEOUT: Duration = Duration::from_secs(60);
+const DOWNLOAD_ATTEMPTS: u32 = 3;
+const DOWNLOAD_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// A release asset that can provide the server, in the order installs are attempted.
+struct InstallCandidate<'a> {
+    asset: zed::GithubReleaseAsset,
+    format: AssetFormat,
+    executable_name: &'a str,
+    requires_node: bool,
+}
+
+/// The release asset a managed install runs.
+#[derive(Debug, Clone, Copy)]
+struct ServerAsset {
+    name: &'static str,
+    requires_node: bool,
+    /// Whether the JS bundle may stand in when the native executable is unavailable.
+    js_fallback: bool,
+}
+
+/// The server to launch and, for managed installs, the marker it writes once it has started.
+struct ResolvedServer {
+    path: PathBuf,
+    requires_node: bool,
+    started_marker: Option<PathBuf>,
+}
+
+impl ResolvedServer {
+    fn unmanaged(path: PathBuf, requires_node: bool) -> Self {
+        Self {
+            path,
+            requires_node,
+            started_marker: None,
+        }
+    }
+}
 
-struct WebComponentsExtension;
+/// What selects a managed server: update channel, followed release, release source and runtime.
+type ServerCacheKey = (ReleaseChannel, ReleaseSelection, ReleaseSource, RuntimeMode);
+
+#[derive(Default)]
+struct WebComponentsExtension {
+    /// Managed servers already resolved this session, keyed by the settings that selected
+    /// them, so new worktrees and restarts skip the release lookup.
+    resolved_servers: HashMap<ServerCacheKey, (PathBuf, bool)>,
+}
 
 impl WebComponentsExtension {
-    fn resolve_server_script(&self) -> Result<(PathBuf, bool)> {
+    fn resolve_server_script(
+        &mut self,
+        language_server_id: &LanguageServerId,
+        worktree: &zed::Worktree,
+        settings: &ExtensionSettings,
+        binary: Option<&CommandSettings>,
+    ) -> Result<ResolvedServer> {
         println!("[wc-tools] Resolving server script...");
+        if let Some(path) = binary.and_then(|binary| binary.path.as_deref()) {
+            let custom_path = PathBuf::from(path);
+            let requires_node = Self::is_node_script(&custom_path);
+            return Ok(ResolvedServer::unmanaged(custom_path, requires_node));
+        }
+
         if let Ok(custom) = env::var(CUSTOM_SERVER_ENV) {
             let custom_path = PathBuf::from(custom);
-            return Ok((custom_path.clone(), Self::is_node_script(&custom_path)));
+            let requires_node = Self::is_node_script(&custom_path);
+            return Ok(ResolvedServer::unmanaged(custom_path, requires_node));
         }
 
-        let extension_root = env::current_dir()
-            .map_err(|err| format!("failed to resolve extension root: {err}"))?;
+        // The project's own devDependency always runs through Node, whatever its bin file is named.
+        if let Some(local_script) = project::local_server_script(worktree) {
+            println!(
+                "[wc-tools] Using project language server at {}",
+                local_script.display()
+            );
+            return Ok(ResolvedServer::unmanaged(local_script, true));
+        }
+
+        let extension_root =
+            env::current_dir().map_err(|err| format!("failed to resolve extension root: {err}"))?;
         let extension_root = match fs::canonicalize(&extension_root) {
             Ok(path) => path,
             Err(err) => {
@@ -29,188 +137,1095 @@ impl WebComponentsExtension {
                 extension_root
             }
         };
-        let (preferred_asset, preferred_requires_node) = Self::server_asset_for_platform();
-        let script = extension_root
-            .join("server/bin")
-            .join(preferred_asset);
-        let version_marker = extension_root.join(SERVER_VERSION_MARKER);
-
-        self.ensure_latest_language_server(
-            &script,
-            &version_marker,
-            preferred_asset,
-            preferred_requires_node,
-        )
+        let cache_key = (
+            settings.channel,
+            Self::release_selection(settings, worktree),
+            settings.release_source(),
+            settings.runtime,
+        );
+        let preferred = Self::preferred_server_asset(settings.runtime)?;
+        let server_dir = extension_root.join(settings.channel.server_dir());
+        let script = server_dir.join(preferred.name);
+        let bundled = BundledServer::find(&extension_root);
+
+        let (resolved, freshly_resolved) = match self.resolved_servers.get(&cache_key) {
+            Some(resolved) if resolved.0.exists() => (resolved.clone(), false),
+            _ => {
+                let legacy_dir = extension_root.join(settings.channel.legacy_server_dir());
+                let keep_bundle = bundled
+                    .as_ref()
+                    .is_some_and(|bundled| bundled.bin_dir == legacy_dir);
+                for migrated in versions::migrate_legacy_dir(&legacy_dir, &server_dir, keep_bundle)
+                {
+                    println!(
+                        "[wc-tools] Moved language server asset from {} to {}",
+                        legacy_dir.display(),
+                        migrated.display()
+                    );
+                }
+                let result = self.ensure_latest_language_server(
+                    language_server_id,
+                    &script,
+                    preferred,
+                    &cache_key.1,
+                    bundled.as_ref(),
+                    settings,
+                );
+                let status = match &result {
+                    Ok(_) => zed::LanguageServerInstallationStatus::None,
+                    Err(err) => zed::LanguageServerInstallationStatus::Failed(err.clone()),
+                };
+                zed::set_language_server_installation_status(language_server_id, &status);
+                (result?, true)
+            }
+        };
+
+        // A server waiting for a debugger to attach has not failed to start.
+        let launch = if settings.launch_mode == LaunchMode::Normal {
+            Self::check_launch_history(
+                language_server_id,
+                &server_dir,
+                preferred,
+                cache_key.1 != ReleaseSelection::Latest,
+                resolved,
+            )
+        } else {
+            ResolvedServer::unmanaged(resolved.0, resolved.1)
+        };
+        if freshly_resolved {
+            Self::remove_stale_assets(&server_dir, &launch.path);
+        }
+        self.resolved_servers
+            .insert(cache_key, (launch.path.clone(), launch.requires_node));
+        Ok(launch)
     }
 
-    fn ensure_latest_language_server(
-        &self,
-        script: &PathBuf,
-        version_marker: &PathBuf,
-        preferred_asset: &str,
-        preferred_requires_node: bool,
-    ) -> Result<(PathBuf, bool)> {
-        let release = match zed::latest_github_release(
-            GITHUB_REPO,
-            zed::GithubReleaseOptions {
-                require_assets: true,
-                pre_release: false,
-            },
-        ) {
-            Ok(release) => release,
-            Err(err) if script.exists() => {
+    /// The release to run: the `version` setting, else the server version the worktree's
+    /// `package.json` declares, else the newest compatible release.
+    fn release_selection(
+        settings: &ExtensionSettings,
+        worktree: &zed::Worktree,
+    ) -> ReleaseSelection {
+        if let Some(tag) = settings.pinned_release_tag() {
+            return ReleaseSelection::Pinned(tag);
+        }
+        let Some(declared) = project::declared_server_range(worktree) else {
+            return ReleaseSelection::Latest;
+        };
+        match semver::VersionReq::parse(&declared) {
+            Some(requirement) => {
+                println!("[wc-tools] Project declares language server {declared}");
+                ReleaseSelection::Project {
+                    declared,
+                    requirement,
+                }
+            }
+            None => {
                 println!(
-                    "[wc-tools] Failed to check GitHub releases: {err}. Using existing server at {}",
-                    script.display()
+                    "[wc-tools] Ignoring language server requirement {declared:?}, which is not a version range"
                 );
-                return Ok((script.clone(), preferred_requires_node));
-            }
-            Err(err) => {
-                return Err(format!(
-                    "unable to resolve language server release ({}); no existing binary found at {}",
-                    err,
-                    script.display()
-                )
-                .into());
+                ReleaseSelection::Latest
             }
-        };
+        }
+    }
 
-        let preferred_release_asset = release
-            .assets
-            .iter()
-            .find(|asset| asset.name == preferred_asset)
-            .cloned();
-        let js_release_asset = release
-            .assets
-            .iter()
-            .find(|asset| asset.name == JS_ASSET_NAME)
-            .cloned();
-        let js_path = script
-            .parent()
-            .unwrap_or_else(|| script.as_path())
-            .join(JS_ASSET_NAME);
-
-        let current_version = fs::read_to_string(version_marker)
+    /// The server options for this worktree: `wc.config.json`, then Zed settings. Problems with
+    /// either are logged rather than returned, so they never keep the server from starting.
+    fn server_config(server_id: &LanguageServerId, worktree: &zed::Worktree) -> config::WcConfig {
+        let settings = LspSettings::for_worktree(server_id.as_ref(), worktree)
             .ok()
-            .map(|contents| contents.trim().to_owned());
+            .and_then(|lsp_settings| lsp_settings.settings);
+        let config_file = worktree.read_text_file(config::CONFIG_FILE_NAME).ok();
+        let home = worktree
+            .shell_env()
+            .into_iter()
+            .find(|(name, _)| name == "HOME" || name == "USERPROFILE")
+            .map(|(_, value)| value);
+        let (config, warnings) = config::resolve(
+            Path::new(&worktree.root_path()),
+            home.as_deref(),
+            config_file.as_deref(),
+            settings.as_ref(),
+        );
+        for warning in warnings {
+            println!("[wc-tools] {warning}");
+        }
+        config
+    }
 
-        let up_to_date = script.exists()
-            && current_version
-                .as_deref()
-                .map(|version| version == release.version)
-                .unwrap_or(false);
+    /// Deletes assets left behind by earlier installs, unless another Zed window is
+    /// installing into `server_dir` right now.
+    fn remove_stale_assets(server_dir: &Path, in_use: &Path) {
+        let Ok(_lock) =
+            InstallLock::acquire(&server_dir.join(install::INSTALL_LOCK_NAME), Duration::ZERO)
+        else {
+            return;
+        };
+        let current_version = InstallManifest::read(server_dir).map(|manifest| manifest.version);
+        for removed in
+            VersionStore::new(server_dir).remove_stale_assets(current_version.as_deref(), in_use)
+        {
+            println!(
+                "[wc-tools] Removed stale language server asset {}",
+                removed.display()
+            );
+        }
+    }
 
-        if up_to_date {
+    /// Counts launches of a managed server version. Once a version has gone
+    /// `MAX_FAILED_LAUNCHES` launches in a row without reporting a successful start, switches
+    /// back to the last known-good version and explains why in the server status. Versions
+    /// that predate the started marker are launched as they are.
+    fn check_launch_history(
+        language_server_id: &LanguageServerId,
+        server_dir: &Path,
+        preferred: ServerAsset,
+        pinned: bool,
+        (path, requires_node): (PathBuf, bool),
+    ) -> ResolvedServer {
+        let store = VersionStore::new(server_dir);
+        let Some(version) = store.version_of(&path) else {
+            return ResolvedServer::unmanaged(path, requires_node);
+        };
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let versions::Launch {
+            started_marker,
+            failed_launches,
+        } = store.record_launch(&version, &path, now);
+        let launch = |path: PathBuf, requires_node: bool, started_marker| ResolvedServer {
+            path,
+            requires_node,
+            started_marker,
+        };
+        if failed_launches < versions::MAX_FAILED_LAUNCHES {
+            return launch(path, requires_node, started_marker);
+        }
+        if pinned {
             println!(
-                "[wc-tools] Using cached language server {} at {}",
-                release.version,
-                script.display()
+                "[wc-tools] Language server {version} failed to start {failed_launches} times in a row; not rolling back a version chosen by the `version` setting or the project's package.json"
             );
-            return Ok((script.clone(), preferred_requires_node));
+            return launch(path, requires_node, started_marker);
         }
 
-        if js_path.exists()
-            && current_version
-                .as_deref()
-                .map(|version| version == release.version)
-                .unwrap_or(false)
+        let Some((target, target_manifest)) = store.rollback_target(&version).and_then(|target| {
+            let manifest = InstallManifest::read(&store.version_dir(&target))
+                .filter(|manifest| Self::manifest_matches(manifest, preferred))
+                .or_else(|| {
+                    let (path, requires_node) =
+                        Self::find_installed_server(server_dir, preferred, Some(&target))?;
+                    Some(InstallManifest::for_existing(
+                        server_dir,
+                        &target,
+                        &path,
+                        requires_node,
+                        Self::unix_now(),
+                    ))
+                })?;
+            Some((target, manifest))
+        }) else {
+            println!(
+                "[wc-tools] Language server {version} failed to start {failed_launches} times in a row and no previous version is installed to roll back to"
+            );
+            return launch(path, requires_node, started_marker);
+        };
+
+        if let Err(err) = store
+            .reject(&version)
+            .and_then(|_| target_manifest.write(server_dir))
+        {
+            println!("[wc-tools] Failed to record rollback from {version}: {err}");
+        }
+        let target_path = target_manifest.executable_path(server_dir);
+        let target_launch = store.record_launch(&target, &target_path, now);
+        let message = format!(
+            "language server {version} failed to start {failed_launches} times in a row, so the extension rolled back to {target}. Set `version` under lsp.wc-language-server.settings to retry {version}."
+        );
+        println!("[wc-tools] {message}");
+        zed::set_language_server_installation_status(
+            language_server_id,
+            &zed::LanguageServerInstallationStatus::Failed(message),
+        );
+        launch(
+            target_path,
+            target_manifest.requires_node(),
+            target_launch.started_marker,
+        )
+    }
+
+    fn ensure_latest_language_server(
+        &self,
+        language_server_id: &LanguageServerId,
+        script: &Path,
+        preferred: ServerAsset,
+        selection: &ReleaseSelection,
+        bundled: Option<&BundledServer>,
+        settings: &ExtensionSettings,
+    ) -> Result<(PathBuf, bool)> {
+        let server_dir = script.parent().unwrap_or(script);
+        let store = VersionStore::new(server_dir);
+        let current = InstallManifest::load(server_dir, Self::unix_now(), |version| {
+            Self::find_installed_server(server_dir, preferred, Some(version))
+        });
+        // A project's version is looked up among every installed version, since installing it
+        // does not change the server other worktrees run.
+        let installed = match selection {
+            ReleaseSelection::Project { .. } => {
+                Self::installed_matching(server_dir, preferred, selection).or(current.clone())
+            }
+            _ => current.clone(),
+        };
+
+        if let Some(bundled) = bundled
+            && let Some(adopted) =
+                Self::adopt_bundled_server(server_dir, preferred, selection, bundled)
+        {
+            return Ok(adopted);
+        }
+
+        // A pinned release that is installed never needs the release source, so it keeps
+        // working offline or while a mirror is down.
+        if let ReleaseSelection::Pinned(tag) = selection
+            && let Some(cached) =
+                Self::cached_server(server_dir, preferred, installed.as_ref(), tag).or_else(|| {
+                    Self::reuse_installed_release(server_dir, preferred, selection, tag)
+                })
+        {
+            return Ok(cached);
+        }
+
+        let last_check_marker = server_dir.join(LAST_UPDATE_CHECK_MARKER);
+        if !matches!(selection, ReleaseSelection::Pinned(_))
+            && installed
+                .as_ref()
+                .is_some_and(|manifest| selection.accepts(&manifest.version))
+            && !Self::is_update_check_due(
+                Self::read_last_update_check(&last_check_marker),
+                Self::unix_now(),
+                settings.update_check_interval(),
+            )
+            && let Some(cached) = Self::installed_server(server_dir, preferred, installed.as_ref())
         {
             println!(
-                "[wc-tools] Using cached language server {} at {}",
-                release.version,
-                js_path.display()
+                "[wc-tools] Checked for language server updates recently. Using existing server at {}",
+                cached.0.display()
             );
-            return Ok((js_path, true));
+            return Ok(cached);
         }
 
-        let (asset, target_path, requires_node) = match preferred_release_asset {
-            Some(asset) => (asset, script.clone(), preferred_requires_node),
-            None if script.exists() => {
+        zed::set_language_server_installation_status(
+            language_server_id,
+            &zed::LanguageServerInstallationStatus::CheckingForUpdate,
+        );
+        let source = settings.release_source();
+        let lookup = match selection {
+            ReleaseSelection::Pinned(tag) => Ok(source.release_by_tag(tag).map_err(|err| {
+                format!(
+                    "pinned language server version {tag} was not found in {} ({err}); check the `version` setting under lsp.wc-language-server",
+                    source.describe()
+                )
+            })?),
+            ReleaseSelection::Latest => {
+                source.latest_release(settings.channel.includes_pre_releases())
+            }
+            ReleaseSelection::Project {
+                declared,
+                requirement,
+            } => source.matching_release(declared, requirement),
+        };
+        let release = match lookup {
+            Ok(release) => release,
+            Err(err) => {
+                return Self::fall_back_to_installed(
+                    server_dir,
+                    preferred,
+                    installed.as_ref(),
+                    &format!("failed to check {} ({err})", source.describe()),
+                );
+            }
+        };
+
+        Self::record_update_check(&last_check_marker);
+
+        if *selection == ReleaseSelection::Latest && store.is_rejected(&release.version) {
+            return Self::fall_back_to_installed(
+                server_dir,
+                preferred,
+                installed.as_ref(),
+                &format!(
+                    "language server {} was rolled back after failing to start",
+                    release.version
+                ),
+            );
+        }
+
+        if let Some(cached) =
+            Self::cached_server(server_dir, preferred, installed.as_ref(), &release.version)
+                .or_else(|| {
+                    Self::reuse_installed_release(
+                        server_dir,
+                        preferred,
+                        selection,
+                        &release.version,
+                    )
+                })
+        {
+            return Ok(cached);
+        }
+
+        let find_asset = |name: &str| release.assets.iter().find(|asset| asset.name == name);
+        let platform_release_asset = archive::asset_candidates(preferred.name)
+            .into_iter()
+            .filter(|(_, format)| !format.is_compressed() || source.supports_compressed_assets())
+            .find_map(|(name, format)| find_asset(&name).map(|asset| (asset.clone(), format)));
+
+        // A pinned or project release must not be substituted with whatever version happens
+        // to be cached.
+        let reuse_existing = *selection == ReleaseSelection::Latest;
+        let mut candidates = Vec::new();
+        match platform_release_asset {
+            Some((asset, format)) => candidates.push(InstallCandidate {
+                asset,
+                format,
+                executable_name: preferred.name,
+                requires_node: preferred.requires_node,
+            }),
+            None => {
+                if reuse_existing
+                    && let Some(existing) = Self::installed_native_server(
+                        server_dir,
+                        preferred.name,
+                        installed.as_ref().map(|manifest| manifest.version.as_str()),
+                    )
+                {
+                    println!(
+                        "[wc-tools] Latest release {} is missing asset {}. Using existing server at {}",
+                        release.version,
+                        preferred.name,
+                        existing.display()
+                    );
+                    return Ok((existing, preferred.requires_node));
+                }
+            }
+        }
+        // The JS bundle doubles as the fallback for a native executable that fails its health check.
+        if preferred.js_fallback
+            && let Some(asset) = find_asset(JS_ASSET_NAME)
+        {
+            candidates.push(InstallCandidate {
+                asset: asset.clone(),
+                format: AssetFormat::Uncompressed,
+                executable_name: JS_ASSET_NAME,
+                requires_node: true,
+            });
+        }
+
+        if candidates.is_empty() {
+            if reuse_existing
+                && let Some(existing) =
+                    Self::installed_server(server_dir, preferred, installed.as_ref())
+            {
                 println!(
                     "[wc-tools] Latest release {} is missing asset {}. Using existing server at {}",
                     release.version,
-                    preferred_asset,
-                    script.display()
+                    JS_ASSET_NAME,
+                    existing.0.display()
                 );
-                return Ok((script.clone(), preferred_requires_node));
+                return Ok(existing);
             }
-            None => match js_release_asset {
-                Some(asset) => (asset, js_path.clone(), true),
-                None if js_path.exists() => {
+            return Err(format!(
+                "latest release {} is missing the {} asset{} and no cached server exists at {}",
+                release.version,
+                preferred.name,
+                if preferred.js_fallback {
+                    format!(" and its {JS_ASSET_NAME} fallback")
+                } else {
+                    String::new()
+                },
+                script.display()
+            ));
+        }
+
+        fs::create_dir_all(server_dir).map_err(|err| {
+            format!("failed to create language server directory {server_dir:?}: {err}")
+        })?;
+        let fall_back = |reason: &str| {
+            Self::fall_back_to_installed(server_dir, preferred, installed.as_ref(), reason)
+        };
+
+        // Another Zed window may be installing the same release; wait for it rather than
+        // racing it for the same files.
+        let _lock = match InstallLock::acquire(
+            &server_dir.join(install::INSTALL_LOCK_NAME),
+            INSTALL_LOCK_TIMEOUT,
+        ) {
+            Ok(lock) => lock,
+            Err(err) => return fall_back(&err),
+        };
+        if let Some(cached) = Self::cached_server(
+            server_dir,
+            preferred,
+            InstallManifest::read(server_dir).as_ref(),
+            &release.version,
+        )
+        .or_else(|| {
+            Self::reuse_installed_release(server_dir, preferred, selection, &release.version)
+        }) {
+            return Ok(cached);
+        }
+
+        let mut failures = Vec::new();
+        for candidate in &candidates {
+            match Self::install_verified_asset(
+                language_server_id,
+                &source,
+                &release,
+                candidate,
+                server_dir,
+                settings,
+            ) {
+                Ok(manifest) => {
+                    if selection.updates_current() {
+                        manifest.write(server_dir)?;
+                    } else {
+                        manifest.write_version_record(server_dir)?;
+                    }
+                    let current_version = current
+                        .as_ref()
+                        .filter(|_| !selection.updates_current())
+                        .map(|manifest| manifest.version.as_str());
+                    if let Err(err) = store.record_install(
+                        &release.version,
+                        settings.versions_to_keep(),
+                        current_version,
+                    ) {
+                        println!("[wc-tools] Failed to prune old language server versions: {err}");
+                    }
+                    return Ok((
+                        manifest.executable_path(server_dir),
+                        manifest.requires_node(),
+                    ));
+                }
+                Err(err) => {
                     println!(
-                        "[wc-tools] Latest release {} is missing asset {}. Using existing server at {}",
-                        release.version,
-                        JS_ASSET_NAME,
-                        js_path.display()
+                        "[wc-tools] Failed to install {} from release {}: {err}",
+                        candidate.asset.name, release.version
                     );
-                    return Ok((js_path, true));
+                    failures.push(format!("{}: {err}", candidate.asset.name));
                 }
-                None => {
-                    return Err(format!(
-                        "latest release {} is missing required assets {} or {} and no cached server exists at {}",
-                        release.version,
-                        preferred_asset,
-                        JS_ASSET_NAME,
-                        script.display()
+            }
+        }
+
+        let reason = format!(
+            "failed to install language server {} ({})",
+            release.version,
+            failures.join("; ")
+        );
+        if settings.runtime == RuntimeMode::Native {
+            return Err(format!(
+                "{reason}; `runtime` is set to \"native\", so the extension does not fall back to another server"
+            ));
+        }
+        fall_back(&reason)
+    }
+
+    fn fall_back_to_installed(
+        server_dir: &Path,
+        preferred: ServerAsset,
+        installed: Option<&InstallManifest>,
+        reason: &str,
+    ) -> Result<(PathBuf, bool)> {
+        match Self::installed_server(server_dir, preferred, installed) {
+            Some((path, requires_node)) => {
+                println!(
+                    "[wc-tools] {reason}. Using existing server at {}",
+                    path.display()
+                );
+                Ok((path, requires_node))
+            }
+            None => Err(format!(
+                "{reason}; no cached language server exists in {}",
+                server_dir.display()
+            )),
+        }
+    }
+
+    /// Makes the server bundled with the extension the current one when `selection` accepts
+    /// it and no installed version is as new, so the first launch needs no download. The
+    /// next launch checks for updates as usual. Only applies to selections that change the
+    /// current server, and never to a version that was rolled back.
+    fn adopt_bundled_server(
+        server_dir: &Path,
+        preferred: ServerAsset,
+        selection: &ReleaseSelection,
+        bundled: &BundledServer,
+    ) -> Option<(PathBuf, bool)> {
+        let store = VersionStore::new(server_dir);
+        if !selection.updates_current()
+            || !selection.accepts(&bundled.version)
+            || store.is_rejected(&bundled.version)
+        {
+            return None;
+        }
+        let parse = |tag: &str| semver::Version::parse(source::version_number(tag));
+        let bundled_version = parse(&bundled.version)?;
+        let newest_known = store
+            .installed()
+            .into_iter()
+            .chain(InstallManifest::read(server_dir).map(|manifest| manifest.version))
+            .filter_map(|tag| parse(&tag))
+            .max();
+        if newest_known.is_some_and(|known| known >= bundled_version) {
+            return None;
+        }
+
+        let (path, requires_node) = Self::find_installed_server(&bundled.bin_dir, preferred, None)?;
+        let manifest = InstallManifest::for_existing(
+            server_dir,
+            &bundled.version,
+            &path,
+            requires_node,
+            Self::unix_now(),
+        );
+        if let Err(err) = fs::create_dir_all(server_dir)
+            .map_err(|err| err.to_string())
+            .and_then(|_| manifest.write(server_dir))
+        {
+            println!("[wc-tools] Failed to record the bundled language server: {err}");
+            return None;
+        }
+        println!(
+            "[wc-tools] Using bundled language server {} at {}; updates are checked on a later launch",
+            bundled.version,
+            path.display()
+        );
+        Some((path, requires_node))
+    }
+
+    /// The newest installed version that `selection` accepts, with its install record.
+    fn installed_matching(
+        server_dir: &Path,
+        preferred: ServerAsset,
+        selection: &ReleaseSelection,
+    ) -> Option<InstallManifest> {
+        let store = VersionStore::new(server_dir);
+        let version = store
+            .installed()
+            .into_iter()
+            .filter(|version| selection.accepts(version))
+            .filter_map(|tag| Some((semver::Version::parse(source::version_number(&tag))?, tag)))
+            .max_by(|(left, _), (right, _)| left.cmp(right))
+            .map(|(_, tag)| tag)?;
+        InstallManifest::read(&store.version_dir(&version))
+            .filter(|manifest| Self::manifest_matches(manifest, preferred))
+            .or_else(|| {
+                let (path, requires_node) =
+                    Self::find_installed_server(server_dir, preferred, Some(&version))?;
+                Some(InstallManifest::for_existing(
+                    server_dir,
+                    &version,
+                    &path,
+                    requires_node,
+                    Self::unix_now(),
+                ))
+            })
+    }
+
+    /// Reuses `version` if it is already installed in its own directory, for example because
+    /// a project asked for it, making it the current server when `selection` follows one.
+    fn reuse_installed_release(
+        server_dir: &Path,
+        preferred: ServerAsset,
+        selection: &ReleaseSelection,
+        version: &str,
+    ) -> Option<(PathBuf, bool)> {
+        let manifest = InstallManifest::read(&VersionStore::new(server_dir).version_dir(version))?;
+        let cached = Self::cached_server(server_dir, preferred, Some(&manifest), version)?;
+        if selection.updates_current()
+            && let Err(err) = manifest.write(server_dir)
+        {
+            println!("[wc-tools] Failed to record {version} as the current language server: {err}");
+        }
+        Some(cached)
+    }
+
+    fn read_last_update_check(marker: &Path) -> Option<u64> {
+        fs::read_to_string(marker).ok()?.trim().parse().ok()
+    }
+
+    fn record_update_check(marker: &Path) {
+        if let Err(err) = install::write_atomically(marker, &Self::unix_now().to_string()) {
+            println!(
+                "[wc-tools] Failed to record update check at {}: {err}",
+                marker.display()
+            );
+        }
+    }
+
+    fn unix_now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default()
+    }
+
+    /// A zero interval disables throttling; a timestamp from the future (clock changes)
+    /// is treated as stale.
+    fn is_update_check_due(last_checked: Option<u64>, now: u64, interval: Duration) -> bool {
+        match last_checked {
+            Some(last_checked) if !interval.is_zero() && last_checked <= now => {
+                now - last_checked >= interval.as_secs()
+            }
+            _ => true,
+        }
+    }
+
+    /// Returns the cached server if the install manifest says it was installed from `version`.
+    fn cached_server(
+        server_dir: &Path,
+        preferred: ServerAsset,
+        installed: Option<&InstallManifest>,
+        version: &str,
+    ) -> Option<(PathBuf, bool)> {
+        let installed = installed.filter(|manifest| manifest.version == version)?;
+        let (path, requires_node) = Self::installed_server(server_dir, preferred, Some(installed))?;
+        println!(
+            "[wc-tools] Using cached language server {} at {}",
+            version,
+            path.display()
+        );
+        Some((path, requires_node))
+    }
+
+    /// The server recorded in the install manifest, if it suits the configured runtime and is
+    /// still on disk; otherwise whatever server is installed for the manifest's version.
+    fn installed_server(
+        server_dir: &Path,
+        preferred: ServerAsset,
+        installed: Option<&InstallManifest>,
+    ) -> Option<(PathBuf, bool)> {
+        if let Some(manifest) = installed
+            && Self::manifest_matches(manifest, preferred)
+        {
+            let executable = manifest.executable_path(server_dir);
+            if executable.exists() {
+                return Some((executable, manifest.requires_node()));
+            }
+        }
+        Self::find_installed_server(
+            server_dir,
+            preferred,
+            installed.map(|manifest| manifest.version.as_str()),
+        )
+    }
+
+    /// Whether the recorded install can serve `preferred`: the JS bundle where Node is
+    /// allowed, or a native asset built for this platform.
+    fn manifest_matches(manifest: &InstallManifest, preferred: ServerAsset) -> bool {
+        match manifest.runtime {
+            RuntimeKind::Node => {
+                manifest.asset == JS_ASSET_NAME
+                    && (preferred.requires_node || preferred.js_fallback)
+            }
+            RuntimeKind::Native => {
+                !preferred.requires_node
+                    && archive::asset_candidates(preferred.name)
+                        .iter()
+                        .any(|(name, _)| *name == manifest.asset)
+            }
+        }
+    }
+
+    /// Searches for the server installed for `current_version`, preferring the native
+    /// executable over the JS bundle. Servers downloaded before versions got their own
+    /// directory are still found directly in `server_dir`.
+    fn find_installed_server(
+        server_dir: &Path,
+        preferred: ServerAsset,
+        current_version: Option<&str>,
+    ) -> Option<(PathBuf, bool)> {
+        if let Some(version) = current_version {
+            let version_dir = VersionStore::new(server_dir).version_dir(version);
+            if let Some(native) =
+                archive::find_executable(&version_dir, &Self::executable_names(preferred.name))
+            {
+                return Some((native, preferred.requires_node));
+            }
+            let js_path = version_dir.join(JS_ASSET_NAME);
+            if preferred.js_fallback && js_path.exists() {
+                return Some((js_path, true));
+            }
+        }
+
+        let legacy_script = server_dir.join(preferred.name);
+        if legacy_script.exists() {
+            return Some((legacy_script, preferred.requires_node));
+        }
+        let legacy_js_path = server_dir.join(JS_ASSET_NAME);
+        (preferred.js_fallback && legacy_js_path.exists()).then_some((legacy_js_path, true))
+    }
+
+    /// Finds the platform executable in the directory of `current_version`, or a legacy
+    /// download directly in `server_dir`.
+    fn installed_native_server(
+        server_dir: &Path,
+        preferred_asset: &str,
+        current_version: Option<&str>,
+    ) -> Option<PathBuf> {
+        if let Some(version) = current_version
+            && let Some(installed) = archive::find_executable(
+                &VersionStore::new(server_dir).version_dir(version),
+                &Self::executable_names(preferred_asset),
+            )
+        {
+            return Some(installed);
+        }
+
+        let script = server_dir.join(preferred_asset);
+        script.exists().then_some(script)
+    }
+
+    /// File names an archive may use for the server executable. The JS bundle is only ever
+    /// found under its own name, so a native executable is never mistaken for it.
+    fn executable_names(executable_name: &str) -> Vec<&str> {
+        if executable_name == JS_ASSET_NAME {
+            return vec![executable_name];
+        }
+        vec![
+            executable_name,
+            "wc-language-server",
+            "wc-language-server.exe",
+        ]
+    }
+
+    /// Downloads the candidate's asset to a staging directory, checks the server executable
+    /// against the release's `SHA256SUMS` and runs its `--version`, and only then moves it
+    /// into the release's version directory. Returns the install record for the new server.
+    fn install_verified_asset(
+        language_server_id: &LanguageServerId,
+        source: &ReleaseSource,
+        release: &zed::GithubRelease,
+        candidate: &InstallCandidate,
+        server_dir: &Path,
+        settings: &ExtensionSettings,
+    ) -> Result<InstallManifest> {
+        let InstallCandidate {
+            asset,
+            format,
+            executable_name,
+            requires_node,
+        } = candidate;
+        let (format, executable_name) = (*format, *executable_name);
+        let target_path = VersionStore::new(server_dir).version_dir(&release.version);
+        let checksums_asset = release
+            .assets
+            .iter()
+            .find(|candidate| candidate.name == checksum::CHECKSUMS_ASSET_NAME);
+
+        zed::set_language_server_installation_status(
+            language_server_id,
+            &zed::LanguageServerInstallationStatus::Downloading,
+        );
+        let expected = match checksums_asset {
+            Some(checksums_asset) => {
+                let checksums_path = server_dir.join(checksum::CHECKSUMS_ASSET_NAME);
+                install::with_retries(DOWNLOAD_ATTEMPTS, DOWNLOAD_RETRY_DELAY, || {
+                    source.fetch(
+                        checksums_asset,
+                        &checksums_path,
+                        zed::DownloadedFileType::Uncompressed,
                     )
-                    .into());
-                }
+                })?;
+                let checksums = fs::read_to_string(&checksums_path)
+                    .map_err(|err| format!("failed to read {}: {err}", checksums_path.display()))?;
+                // Archives are verified by the executable they contain, so the digest is always
+                // looked up by the uncompressed executable name.
+                let expected = checksum::expected_checksum(&checksums, executable_name)
+                    .ok_or_else(|| {
+                        format!(
+                            "{} for release {} has no entry for {}",
+                            checksum::CHECKSUMS_ASSET_NAME,
+                            release.version,
+                            executable_name
+                        )
+                    })?;
+                Some(expected)
+            }
+            None if Self::predates_verification(&release.version) => {
+                println!(
+                    "[wc-tools] Release {} predates {}; installing it without checksum verification",
+                    release.version,
+                    checksum::CHECKSUMS_ASSET_NAME
+                );
+                None
+            }
+            None => {
+                return Err(format!(
+                    "release {} does not publish {}",
+                    release.version,
+                    checksum::CHECKSUMS_ASSET_NAME
+                ));
             }
         };
 
-        if let Some(parent) = target_path.parent() {
-            fs::create_dir_all(parent).map_err(|err| {
-                format!("failed to create language server directory {parent:?}: {err}")
-            })?;
-        }
+        let staging_path = Self::staging_path(&target_path);
 
-        let script_path = target_path.to_string_lossy().to_string();
         println!(
-            "[wc-tools] Downloading language server {} -> {}",
-            release.version, script_path
+            "[wc-tools] Downloading language server {} ({}) -> {}",
+            release.version,
+            asset.name,
+            staging_path.display()
         );
+        install::with_retries(DOWNLOAD_ATTEMPTS, DOWNLOAD_RETRY_DELAY, || {
+            // Never resume from a partial download left by a failed attempt.
+            Self::remove_path(&staging_path);
+
+            // Archives unpack into the staging directory; single files are written inside it.
+            let download_path = if matches!(format, AssetFormat::GzipTar | AssetFormat::Zip) {
+                staging_path.clone()
+            } else {
+                fs::create_dir_all(&staging_path).map_err(|err| {
+                    format!("failed to create staging directory {staging_path:?}: {err}")
+                })?;
+                staging_path.join(executable_name)
+            };
+            source.fetch(asset, &download_path, format.file_type())
+        })
+        .inspect_err(|_| Self::remove_path(&staging_path))?;
 
-        zed::download_file(
-            &asset.download_url,
-            &script_path,
-            zed::DownloadedFileType::Uncompressed,
-        )?;
+        let Some(staged_executable) =
+            archive::find_executable(&staging_path, &Self::executable_names(executable_name))
+        else {
+            Self::remove_path(&staging_path);
+            return Err(format!("{} does not contain {executable_name}", asset.name));
+        };
 
-        // The server is executed by Node when using the JS bundle; binaries still benefit from the executable bit.
-        let _ = zed::make_file_executable(&script_path);
+        let _ = zed::make_file_executable(&staged_executable.to_string_lossy());
 
-        fs::write(version_marker, release.version).map_err(|err| {
+        let verified = match &expected {
+            Some(expected) => checksum::verify_file(&staged_executable, expected),
+            None => Ok(()),
+        };
+        if let Err(err) = verified.and_then(|_| {
+            let node = requires_node
+                .then(|| node::binary_path(&settings.node))
+                .transpose()?;
+            Self::check_server_health(&staged_executable, node, &release.version)
+        }) {
+            Self::remove_path(&staging_path);
+            return Err(err);
+        }
+
+        Self::remove_path(&target_path);
+        fs::rename(&staging_path, &target_path).map_err(|err| {
             format!(
-                "failed to record downloaded language server version at {}: {err}",
-                version_marker.display()
+                "failed to move verified language server into {}: {err}",
+                target_path.display()
             )
         })?;
 
-        Ok((target_path, requires_node))
+        let relative = staged_executable
+            .strip_prefix(&staging_path)
+            .unwrap_or(&staged_executable);
+        let installed = target_path.join(relative);
+        let now = Self::unix_now();
+        Ok(InstallManifest {
+            version: release.version.clone(),
+            asset: asset.name.clone(),
+            executable: installed
+                .strip_prefix(server_dir)
+                .unwrap_or(&installed)
+                .to_path_buf(),
+            runtime: if *requires_node {
+                RuntimeKind::Node
+            } else {
+                RuntimeKind::Native
+            },
+            source_url: Some(asset.download_url.clone()),
+            checksum: expected,
+            installed_at: now,
+            last_health_check: Some(now),
+        })
+    }
+
+    /// Sibling directory a download is staged in before it replaces `target_path`.
+    fn staging_path(target_path: &Path) -> PathBuf {
+        let mut file_name = target_path.file_name().unwrap_or_default().to_os_string();
+        file_name.push(versions::STAGING_SUFFIX);
+        target_path.with_file_name(file_name)
+    }
+
+    /// Whether `release_version` (a tag or plain version) was published before releases
+    /// carried `SHA256SUMS` and reported their own version.
+    fn predates_verification(release_version: &str) -> bool {
+        semver::Version::parse(source::version_number(release_version))
+            .is_some_and(|version| version.core() < FIRST_VERIFIED_RELEASE)
+    }
+
+    /// Runs `<server> --version`, through `node` for the JS bundle, and confirms the server
+    /// starts and reports `release_version` (or, for older releases, no version at all).
+    fn check_server_health(
+        executable: &Path,
+        node: Option<String>,
+        release_version: &str,
+    ) -> Result<()> {
+        let executable = executable.to_string_lossy().into_owned();
+        let command = if let Some(node) = node {
+            zed::process::Command::new(node).arg(executable)
+        } else {
+            zed::process::Command::new(executable)
+        };
+        let output = command
+            .arg("--version")
+            .output()
+            .map_err(|err| format!("language server failed to start: {err}"))?;
+        Self::validate_version_output(
+            output.status,
+            &output.stdout,
+            &output.stderr,
+            source::version_number(release_version),
+        )
+    }
+
+    fn validate_version_output(
+        status: Option<i32>,
+        stdout: &[u8],
+        stderr: &[u8],
+        expected_version: &str,
+    ) -> Result<()> {
+        if status != Some(0) {
+            let stderr = String::from_utf8_lossy(stderr);
+            return Err(format!(
+                "language server exited with status {} when asked for --version: {}",
+                status.map_or_else(|| "unknown".to_string(), |status| status.to_string()),
+                stderr.trim()
+            ));
+        }
+
+        let reported = String::from_utf8_lossy(stdout);
+        let reported = reported.trim();
+        if reported == UNKNOWN_VERSION_OUTPUT && Self::predates_verification(expected_version) {
+            // The release predates the inlined version, so it can't say which one it is; that
+            // it ran is all we can check. Later releases must name themselves.
+            return Ok(());
+        }
+        if source::version_number(reported) != expected_version {
+            return Err(format!(
+                "language server reported version {reported:?}, expected {expected_version}"
+            ));
+        }
+        Ok(())
+    }
+
+    /// Removes a file or directory tree, ignoring paths that don't exist.
+    fn remove_path(path: &Path) {
+        let _ = if path.is_dir() {
+            fs::remove_dir_all(path)
+        } else {
+            fs::remove_file(path)
+        };
+    }
+
+    /// The asset `runtime` asks for: the platform's native executable (with or without the JS
+    /// bundle as a fallback), or only the JS bundle.
+    fn preferred_server_asset(runtime: RuntimeMode) -> Result<ServerAsset> {
+        if runtime == RuntimeMode::Node {
+            return Ok(ServerAsset {
+                name: JS_ASSET_NAME,
+                requires_node: true,
+                js_fallback: false,
+            });
+        }
+
+        let (name, requires_node) = Self::server_asset_for_platform();
+        if runtime == RuntimeMode::Native && requires_node {
+            return Err(
+                "`runtime` is set to \"native\", but no native language server is published for this platform; use \"auto\" or \"node\" instead".to_string(),
+            );
+        }
+        Ok(ServerAsset {
+            name,
+            requires_node,
+            js_fallback: runtime == RuntimeMode::Auto && !requires_node,
+        })
     }
 
     fn server_asset_for_platform() -> (&'static str, bool) {
-        match env::consts::OS {
-            "windows" => ("wc-language-server-windows-x64.exe", false),
-            "macos" => {
-                if env::consts::ARCH == "aarch64" {
-                    ("wc-language-server-macos-arm64", false)
-                } else {
-                    ("wc-language-server-macos-x64", false)
-                }
+        let (os, arch) = zed::current_platform();
+        Self::server_asset_for(os, arch)
+    }
+
+    fn server_asset_for(os: zed::Os, arch: zed::Architecture) -> (&'static str, bool) {
+        match (os, arch) {
+            (zed::Os::Windows, zed::Architecture::Aarch64) => {
+                ("wc-language-server-windows-arm64.exe", false)
             }
-            "linux" => {
-                if env::consts::ARCH == "aarch64" {
-                    ("wc-language-server-linux-arm64", false)
-                } else {
-                    ("wc-language-server-linux-x64", false)
-                }
+            (zed::Os::Windows, zed::Architecture::X8664) => {
+                ("wc-language-server-windows-x64.exe", false)
             }
-            _ => (JS_ASSET_NAME, true),
+            (zed::Os::Mac, zed::Architecture::Aarch64) => ("wc-language-server-macos-arm64", false),
+            (zed::Os::Mac, zed::Architecture::X8664) => ("wc-language-server-macos-x64", false),
+            (zed::Os::Linux, zed::Architecture::Aarch64) => {
+                ("wc-language-server-linux-arm64", false)
+            }
+            (zed::Os::Linux, zed::Architecture::X8664) => ("wc-language-server-linux-x64", false),
+            // No native executable is published for 32-bit hosts.
+            (_, zed::Architecture::X86) => (JS_ASSET_NAME, true),
+        }
+    }
+
+    /// Tells the user where to attach a debugger. The installation status is the only place
+    /// an extension can show a message, and only as a failure, so it is also logged.
+    fn announce_inspector(
+        language_server_id: &LanguageServerId,
+        launch_mode: LaunchMode,
+        port: u16,
+    ) {
+        let waiting = if launch_mode == LaunchMode::DebugBrk {
+            " and waits for a debugger before starting"
+        } else {
+            ""
+        };
+        let message = format!(
+            "debug launch mode: the language server listens for a debugger on 127.0.0.1:{port}{waiting}. Open chrome://inspect in Chrome (add localhost:{port} under Configure) or find the DevTools URL at http://127.0.0.1:{port}/json/list. Set `launchMode` back to \"normal\" under lsp.wc-language-server.settings when done."
+        );
+        println!("[wc-tools] {message}");
+        zed::set_language_server_installation_status(
+            language_server_id,
+            &zed::LanguageServerInstallationStatus::Failed(message),
+        );
+    }
+
+    /// Builds the launch command, appending the user's `binary.arguments` after the
+    /// built-in ones. The environment is the worktree's shell environment, without variables
+    /// that would break the server, with `binary.env` layered on top.
+    fn server_command(
+        command: String,
+        mut args: Vec<String>,
+        shell_env: zed::EnvVars,
+        binary: Option<&CommandSettings>,
+    ) -> zed::Command {
+        let mut env: BTreeMap<_, _> = node::sanitize_env(shell_env).into_iter().collect();
+        if let Some(binary) = binary {
+            args.extend(binary.arguments.iter().flatten().cloned());
+            env.extend(
+                binary
+                    .env
+                    .iter()
+                    .flatten()
+                    .map(|(key, value)| (key.clone(), value.clone())),
+            );
+        }
+        zed::Command {
+            command,
+            args,
+            env: env.into_iter().collect(),
         }
     }
 
-    fn is_node_script(path: &PathBuf) -> bool {
+    fn is_node_script(path: &Path) -> bool {
         path.extension()
             .and_then(|ext| ext.to_str())
             .map(|ext| matches!(ext, "js" | "cjs" | "mjs"))
@@ -221,30 +1236,69 @@ impl WebComponentsExtension {
 impl zed::Extension for WebComponentsExtension {
     fn new() -> Self {
         println!("[wc-tools] Initializing WebComponentsExtension...");
-        Self
+        Self::default()
     }
 
     fn language_server_command(
         &mut self,
-        _language_server_id: &LanguageServerId,
-        _worktree: &zed::Worktree,
+        language_server_id: &LanguageServerId,
+        worktree: &zed::Worktree,
     ) -> Result<zed::Command> {
         println!("[wc-tools] Resolving language server command...");
-        let (server_path, requires_node) = self.resolve_server_script()?;
-        let server_path_string = server_path.to_string_lossy().to_string();
-        let (command, args) = if requires_node {
-            (
-                zed::node_binary_path()?,
-                vec![server_path_string, "--stdio".to_string()],
-            )
+        let lsp_settings =
+            LspSettings::for_worktree(language_server_id.as_ref(), worktree).unwrap_or_default();
+        let mut settings = ExtensionSettings::from_value(lsp_settings.settings.as_ref());
+        let inspect_flag = settings.launch_mode.inspect_flag();
+        if inspect_flag.is_some() {
+            // Only the JS server can run under the Node.js inspector.
+            settings.runtime = RuntimeMode::Node;
+        }
+        if !settings.force_enable {
+            // Monorepo roots often have neither; their workspace packages do.
+            let marker = project::web_components_marker(worktree)
+                .or_else(|| workspace::ProjectRoots::discover(worktree).marker());
+            match marker {
+                Some(marker) => println!("[wc-tools] Starting language server: {marker}"),
+                None => {
+                    return Err(format!(
+                        "not a web-components project: {} has no wc.config.*, custom-elements.json, or package.json `customElements` field, no workspace package has one, and no dependency publishes custom elements. Set `forceEnable` to true under lsp.wc-language-server.settings to start the server anyway",
+                        worktree.root_path()
+                    ));
+                }
+            }
+        }
+        let binary = lsp_settings.binary.as_ref();
+        let server = self.resolve_server_script(language_server_id, worktree, &settings, binary)?;
+        let server_path_string = server.path.to_string_lossy().to_string();
+        let mut shell_env = worktree.shell_env();
+        let (command, args) = if server.requires_node {
+            let mut args = settings.node.arguments.clone();
+            if let Some(flag) = inspect_flag {
+                args.push(format!("{flag}=127.0.0.1:{}", settings.inspect_port()));
+                shell_env.push((SERVER_DEBUG_ENV.to_string(), "1".to_string()));
+                Self::announce_inspector(
+                    language_server_id,
+                    settings.launch_mode,
+                    settings.inspect_port(),
+                );
+            }
+            args.extend([server_path_string, "--stdio".to_string()]);
+            (node::binary_path(&settings.node)?, args)
+        } else if inspect_flag.is_some() {
+            return Err(format!(
+                "`launchMode` is set to debug, but {server_path_string} is a native executable that cannot run under the Node.js inspector; point `binary.path` at wc-language-server.js or unset {CUSTOM_SERVER_ENV}"
+            ));
         } else {
             (server_path_string, vec!["--stdio".to_string()])
         };
-        Ok(zed::Command {
-            command,
-            args,
-            env: Default::default(),
-        })
+        let mut command = Self::server_command(command, args, shell_env, binary);
+        if let Some(marker) = server.started_marker {
+            command.env.push((
+                versions::STARTED_MARKER_ENV.to_string(),
+                marker.to_string_lossy().into_owned(),
+            ));
+        }
+        Ok(command)
     }
 
     fn language_server_initialization_options(
@@ -256,7 +1310,13 @@ impl zed::Extension for WebComponentsExtension {
         let initialization_options = LspSettings::for_worktree(server_id.as_ref(), worktree)
             .ok()
             .and_then(|lsp_settings| lsp_settings.initialization_options.clone());
-        Ok(initialization_options)
+        // The server only looks for `wc.config.*` in the worktree root, so tell it where the
+        // workspace packages keep theirs.
+        let roots = workspace::ProjectRoots::discover(worktree);
+        let config = Self::server_config(server_id, worktree);
+        Ok(Some(
+            config.merge_into(roots.merge_into(initialization_options)),
+        ))
     }
 
     fn language_server_workspace_configuration(
@@ -265,12 +1325,238 @@ impl zed::Extension for WebComponentsExtension {
         worktree: &zed::Worktree,
     ) -> Result<Option<zed::serde_json::Value>> {
         println!("[wc-tools] Resolving language server workspace configuration...");
-        let settings = LspSettings::for_worktree(server_id.as_ref(), worktree)
-            .ok()
-            .and_then(|lsp_settings| lsp_settings.settings.clone())
-            .unwrap_or_default();
-        Ok(Some(settings))
+        let config = Self::server_config(server_id, worktree);
+        Ok(Some(config.to_value()))
     }
 }
 
-zed::register_extension!(WebComponentsExtension);
\ No newline at end of file
+zed::register_extension!(WebComponentsExtension);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zed::{Architecture, Os};
+
+    #[test]
+    fn server_asset_for_covers_every_platform() {
+        let cases = [
+            (
+                Os::Mac,
+                Architecture::Aarch64,
+                "wc-language-server-macos-arm64",
+                false,
+            ),
+            (
+                Os::Mac,
+                Architecture::X8664,
+                "wc-language-server-macos-x64",
+                false,
+            ),
+            (Os::Mac, Architecture::X86, JS_ASSET_NAME, true),
+            (
+                Os::Linux,
+                Architecture::Aarch64,
+                "wc-language-server-linux-arm64",
+                false,
+            ),
+            (
+                Os::Linux,
+                Architecture::X8664,
+                "wc-language-server-linux-x64",
+                false,
+            ),
+            (Os::Linux, Architecture::X86, JS_ASSET_NAME, true),
+            (
+                Os::Windows,
+                Architecture::Aarch64,
+                "wc-language-server-windows-arm64.exe",
+                false,
+            ),
+            (
+                Os::Windows,
+                Architecture::X8664,
+                "wc-language-server-windows-x64.exe",
+                false,
+            ),
+            (Os::Windows, Architecture::X86, JS_ASSET_NAME, true),
+        ];
+
+        for (os, arch, asset, requires_node) in cases {
+            assert_eq!(
+                WebComponentsExtension::server_asset_for(os, arch),
+                (asset, requires_node),
+                "unexpected asset for {os:?}/{arch:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn update_check_respects_interval() {
+        let day = Duration::from_secs(24 * 60 * 60);
+        let now = 1_000_000;
+
+        assert!(WebComponentsExtension::is_update_check_due(None, now, day));
+        assert!(!WebComponentsExtension::is_update_check_due(
+            Some(now - 60),
+            now,
+            day
+        ));
+        assert!(WebComponentsExtension::is_update_check_due(
+            Some(now - day.as_secs()),
+            now,
+            day
+        ));
+        assert!(WebComponentsExtension::is_update_check_due(
+            Some(now + 60),
+            now,
+            day
+        ));
+        assert!(WebComponentsExtension::is_update_check_due(
+            Some(now),
+            now,
+            Duration::ZERO
+        ));
+    }
+
+    #[test]
+    fn node_runtime_only_uses_the_js_bundle() {
+        let asset = WebComponentsExtension::preferred_server_asset(RuntimeMode::Node).unwrap();
+        assert_eq!(asset.name, JS_ASSET_NAME);
+        assert!(asset.requires_node);
+        assert!(!asset.js_fallback);
+        assert_eq!(
+            WebComponentsExtension::executable_names(JS_ASSET_NAME),
+            [JS_ASSET_NAME]
+        );
+    }
+
+    #[test]
+    fn validate_version_output_requires_matching_release() {
+        let release = "@wc-toolkit/language-server@0.0.8";
+        let expected = source::version_number(release);
+
+        assert!(
+            WebComponentsExtension::validate_version_output(Some(0), b"0.0.8\n", b"", expected)
+                .is_ok()
+        );
+        assert!(
+            WebComponentsExtension::validate_version_output(Some(0), b"0.0.7\n", b"", expected)
+                .is_err()
+        );
+        assert!(
+            WebComponentsExtension::validate_version_output(
+                Some(1),
+                b"",
+                b"GLIBC_2.34 not found",
+                expected
+            )
+            .is_err()
+        );
+        assert!(
+            WebComponentsExtension::validate_version_output(None, b"0.0.8", b"", expected).is_err()
+        );
+    }
+
+    #[test]
+    fn only_releases_before_checksums_skip_verification() {
+        assert!(WebComponentsExtension::predates_verification(
+            "@wc-toolkit/language-server@0.0.7"
+        ));
+        assert!(!WebComponentsExtension::predates_verification(
+            "@wc-toolkit/language-server@0.0.8"
+        ));
+        assert!(!WebComponentsExtension::predates_verification("1.0.0"));
+    }
+
+    #[test]
+    fn validate_version_output_accepts_releases_without_an_inlined_version() {
+        let expected = source::version_number("@wc-toolkit/language-server@0.0.7");
+
+        assert!(
+            WebComponentsExtension::validate_version_output(Some(0), b"0.0.2\n", b"", expected)
+                .is_ok()
+        );
+        assert!(
+            WebComponentsExtension::validate_version_output(Some(1), b"0.0.2\n", b"", expected)
+                .is_err()
+        );
+
+        let err =
+            WebComponentsExtension::validate_version_output(Some(0), b"0.0.2\n", b"", "0.0.8")
+                .unwrap_err();
+        assert_eq!(
+            err,
+            "language server reported version \"0.0.2\", expected 0.0.8"
+        );
+        assert!(
+            WebComponentsExtension::validate_version_output(Some(0), b"0.0.8\n", b"", "0.0.8")
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn staging_path_is_a_sibling_of_the_version_dir() {
+        assert_eq!(
+            WebComponentsExtension::staging_path(Path::new(
+                "/bin/release-wc-toolkit-language-server-0.0.8"
+            )),
+            Path::new("/bin/release-wc-toolkit-language-server-0.0.8.download")
+        );
+    }
+
+    #[test]
+    fn server_command_layers_user_arguments_and_env_over_the_shell() {
+        let binary = CommandSettings {
+            path: Some("/opt/wc/wc-language-server.js".to_string()),
+            arguments: Some(vec!["--log-level".to_string(), "debug".to_string()]),
+            env: Some(
+                [
+                    ("WC_DEBUG".to_string(), "1".to_string()),
+                    (
+                        "HTTPS_PROXY".to_string(),
+                        "http://proxy.corp:3128".to_string(),
+                    ),
+                ]
+                .into_iter()
+                .collect(),
+            ),
+        };
+        let shell_env = vec![
+            ("PATH".to_string(), "/usr/local/bin:/usr/bin".to_string()),
+            ("HTTPS_PROXY".to_string(), "http://proxy:8080".to_string()),
+            ("NODE_OPTIONS".to_string(), "--inspect".to_string()),
+        ];
+
+        let command = WebComponentsExtension::server_command(
+            "node".to_string(),
+            vec![
+                "/opt/wc/wc-language-server.js".to_string(),
+                "--stdio".to_string(),
+            ],
+            shell_env,
+            Some(&binary),
+        );
+
+        assert_eq!(command.command, "node");
+        assert_eq!(
+            command.args,
+            [
+                "/opt/wc/wc-language-server.js",
+                "--stdio",
+                "--log-level",
+                "debug"
+            ]
+        );
+        assert_eq!(
+            command.env,
+            [
+                (
+                    "HTTPS_PROXY".to_string(),
+                    "http://proxy.corp:3128".to_string()
+                ),
+                ("PATH".to_string(), "/usr/local/bin:/usr/bin".to_string()),
+                ("WC_DEBUG".to_string(), "1".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,224 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+use zed_extension_api::{Result, serde_json};
+
+use crate::{archive, install};
+
+/// Record of the installed server, kept in the server directory and in each version directory.
+pub const MANIFEST_NAME: &str = "install.json";
+/// Plain-text version marker that preceded the manifest.
+const LEGACY_VERSION_MARKER: &str = ".release-version";
+
+/// How the installed server is launched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuntimeKind {
+    Native,
+    Node,
+}
+
+/// What was installed, from where, and when it was last verified.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InstallManifest {
+    /// Release tag the server was installed from.
+    pub version: String,
+    /// Name of the release asset that was downloaded.
+    pub asset: String,
+    /// Server executable, relative to the server directory.
+    pub executable: PathBuf,
+    pub runtime: RuntimeKind,
+    pub source_url: Option<String>,
+    /// SHA-256 digest of the executable, as verified against the release's `SHA256SUMS`.
+    /// `None` for servers that were adopted rather than downloaded and verified.
+    pub checksum: Option<String>,
+    /// Unix timestamps in seconds.
+    pub installed_at: u64,
+    pub last_health_check: Option<u64>,
+}
+
+impl InstallManifest {
+    /// Describes a server that is already on disk but has no manifest of its own. Nothing
+    /// vouches for its contents, so no checksum is recorded.
+    pub fn for_existing(
+        server_dir: &Path,
+        version: &str,
+        executable: &Path,
+        requires_node: bool,
+        installed_at: u64,
+    ) -> Self {
+        Self {
+            version: version.to_owned(),
+            asset: executable
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            executable: executable
+                .strip_prefix(server_dir)
+                .unwrap_or(executable)
+                .to_path_buf(),
+            runtime: if requires_node {
+                RuntimeKind::Node
+            } else {
+                RuntimeKind::Native
+            },
+            source_url: None,
+            checksum: None,
+            installed_at,
+            last_health_check: None,
+        }
+    }
+
+    pub fn read(dir: &Path) -> Option<Self> {
+        let contents = fs::read_to_string(dir.join(MANIFEST_NAME)).ok()?;
+        match serde_json::from_str(&contents) {
+            Ok(manifest) => Some(manifest),
+            Err(err) => {
+                println!(
+                    "[wc-tools] Ignoring invalid {MANIFEST_NAME} in {}: {err}",
+                    dir.display()
+                );
+                None
+            }
+        }
+    }
+
+    /// Reads the manifest of the server installed in `server_dir`, converting a legacy
+    /// `.release-version` marker on the way. `locate` finds the executable installed for a
+    /// version and whether it runs on Node.
+    pub fn load(
+        server_dir: &Path,
+        now: u64,
+        locate: impl FnOnce(&str) -> Option<(PathBuf, bool)>,
+    ) -> Option<Self> {
+        if let Some(manifest) = Self::read(server_dir) {
+            return Some(manifest);
+        }
+
+        let legacy_marker = server_dir.join(LEGACY_VERSION_MARKER);
+        let version = fs::read_to_string(&legacy_marker).ok()?.trim().to_owned();
+        let (executable, requires_node) = locate(&version)?;
+        let installed_at = fs::metadata(&legacy_marker)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| modified.duration_since(std::time::UNIX_EPOCH).ok())
+            .map_or(now, |elapsed| elapsed.as_secs());
+        let manifest = Self::for_existing(
+            server_dir,
+            &version,
+            &executable,
+            requires_node,
+            installed_at,
+        );
+        match manifest.write(server_dir) {
+            Ok(()) => {
+                let _ = fs::remove_file(&legacy_marker);
+                println!(
+                    "[wc-tools] Migrated {LEGACY_VERSION_MARKER} for {version} to {MANIFEST_NAME}"
+                );
+            }
+            Err(err) => println!("[wc-tools] Failed to migrate {LEGACY_VERSION_MARKER}: {err}"),
+        }
+        Some(manifest)
+    }
+
+    /// Records this install as the current one in `server_dir`, keeping a copy in the
+    /// version's directory so a rollback can restore it.
+    pub fn write(&self, server_dir: &Path) -> Result<()> {
+        self.write_version_record(server_dir)?;
+        install::write_atomically(&server_dir.join(MANIFEST_NAME), &self.to_json()?)
+    }
+
+    /// Records this install in its version's directory only, leaving the current server as is.
+    pub fn write_version_record(&self, server_dir: &Path) -> Result<()> {
+        let version_dir = server_dir.join(archive::version_dir_name(&self.version));
+        if !version_dir.is_dir() {
+            return Ok(());
+        }
+        install::write_atomically(&version_dir.join(MANIFEST_NAME), &self.to_json()?)
+    }
+
+    fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self)
+            .map_err(|err| format!("failed to serialize {MANIFEST_NAME}: {err}"))
+    }
+
+    pub fn executable_path(&self, server_dir: &Path) -> PathBuf {
+        server_dir.join(&self.executable)
+    }
+
+    pub fn requires_node(&self) -> bool {
+        self.runtime == RuntimeKind::Node
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    #[test]
+    fn write_and_read_round_trip_with_version_copy() {
+        let dir = TempDir::new("manifest-round-trip");
+        let version = "@wc-toolkit/language-server@0.0.8";
+        let version_dir = dir.join(archive::version_dir_name(version));
+        fs::create_dir_all(&version_dir).unwrap();
+        let manifest = InstallManifest {
+            version: version.to_string(),
+            asset: "wc-language-server-linux-x64.tar.gz".to_string(),
+            executable: Path::new(&archive::version_dir_name(version)).join("wc-language-server"),
+            runtime: RuntimeKind::Native,
+            source_url: Some("https://example.com/wc-language-server-linux-x64.tar.gz".to_string()),
+            checksum: Some("abc123".to_string()),
+            installed_at: 1_700_000_000,
+            last_health_check: Some(1_700_000_000),
+        };
+
+        manifest.write(&dir).unwrap();
+
+        assert_eq!(InstallManifest::read(&dir), Some(manifest.clone()));
+        assert_eq!(InstallManifest::read(&version_dir), Some(manifest.clone()));
+        let contents = fs::read_to_string(dir.join(MANIFEST_NAME)).unwrap();
+        assert!(contents.contains("\"lastHealthCheck\""));
+        assert!(contents.contains("\"runtime\": \"native\""));
+    }
+
+    #[test]
+    fn load_migrates_legacy_version_marker() {
+        let dir = TempDir::new("manifest-migrate");
+        let executable = dir.join("wc-language-server.js");
+        fs::write(&executable, "console.log('hi')").unwrap();
+        fs::write(
+            dir.join(LEGACY_VERSION_MARKER),
+            "@wc-toolkit/language-server@0.0.7\n",
+        )
+        .unwrap();
+
+        let manifest = InstallManifest::load(&dir, 42, |version| {
+            assert_eq!(version, "@wc-toolkit/language-server@0.0.7");
+            Some((executable.clone(), true))
+        })
+        .unwrap();
+
+        assert_eq!(manifest.version, "@wc-toolkit/language-server@0.0.7");
+        assert_eq!(manifest.executable, Path::new("wc-language-server.js"));
+        assert_eq!(manifest.executable_path(&dir), executable);
+        assert!(manifest.requires_node());
+        assert_eq!(manifest.checksum, None);
+        assert!(!dir.join(LEGACY_VERSION_MARKER).exists());
+        assert_eq!(InstallManifest::read(&dir), Some(manifest));
+    }
+
+    #[test]
+    fn load_without_record_or_server_returns_none() {
+        let dir = TempDir::new("manifest-missing");
+        assert_eq!(InstallManifest::load(&dir, 0, |_| None), None);
+
+        fs::write(dir.join(LEGACY_VERSION_MARKER), "0.0.7").unwrap();
+        assert_eq!(InstallManifest::load(&dir, 0, |_| None), None);
+        assert!(dir.join(LEGACY_VERSION_MARKER).exists());
+    }
+}
@@ -0,0 +1,185 @@
+use std::env;
+use zed_extension_api::{self as zed, Result};
+
+use crate::settings::NodeSettings;
+
+/// Overrides the Node.js executable used for the JS server when no `node.path` is set.
+pub const NODE_ENV: &str = "WC_LANGUAGE_SERVER_NODE";
+/// Oldest Node.js release the language server supports (its `engines.node`).
+pub const MINIMUM_NODE_VERSION: (u64, u64, u64) = (18, 0, 0);
+/// Variables that make Node.js think it was forked by another Node.js process, which the
+/// server never is.
+const PARENT_PROCESS_VARS: [&str; 3] = [
+    "NODE_CHANNEL_FD",
+    "NODE_CHANNEL_SERIALIZATION_MODE",
+    "NODE_UNIQUE_ID",
+];
+/// `NODE_OPTIONS` flags that open a debugger port or wait for a debugger to attach. Several
+/// servers started from the same shell would fight over the port or hang on startup.
+const DEBUGGER_OPTIONS: [&str; 2] = ["--inspect", "--debug-port"];
+
+/// The Node.js executable to run the JS server with: the `node.path` setting, then
+/// `WC_LANGUAGE_SERVER_NODE`, then the Node.js that Zed manages. A custom executable is
+/// checked against `MINIMUM_NODE_VERSION` first.
+pub fn binary_path(settings: &NodeSettings) -> Result<String> {
+    let custom = settings
+        .path
+        .as_deref()
+        .map(str::trim)
+        .filter(|path| !path.is_empty())
+        .map(str::to_owned)
+        .or_else(|| env::var(NODE_ENV).ok().filter(|path| !path.is_empty()));
+
+    match custom {
+        Some(path) => {
+            check_installed_version(&path)?;
+            Ok(path)
+        }
+        None => zed::node_binary_path(),
+    }
+}
+
+/// Drops the parts of a shell environment that would break the server: the debugger flags in
+/// `NODE_OPTIONS` (keeping its other options) and the variables in `PARENT_PROCESS_VARS`.
+/// The native server is a Bun executable rather than Node.js, and Bun ignores some of these;
+/// it gets the same environment anyway, so both runtimes start from the same variables.
+pub fn sanitize_env(env: zed::EnvVars) -> zed::EnvVars {
+    env.into_iter()
+        .filter_map(|(name, value)| {
+            if PARENT_PROCESS_VARS.contains(&name.as_str()) {
+                println!("[wc-tools] Not passing {name} to the language server");
+                return None;
+            }
+            if name != "NODE_OPTIONS" {
+                return Some((name, value));
+            }
+            let options = strip_debugger_options(&value)?;
+            Some((name, options))
+        })
+        .collect()
+}
+
+/// Removes `DEBUGGER_OPTIONS` flags (and a separate port argument) from `NODE_OPTIONS`.
+/// Returns `None` when no options are left.
+fn strip_debugger_options(options: &str) -> Option<String> {
+    let is_debugger_flag = |flag: &str| {
+        DEBUGGER_OPTIONS
+            .iter()
+            .any(|blocked| flag.starts_with(blocked))
+    };
+    if !options.split_whitespace().any(is_debugger_flag) {
+        return Some(options.to_owned()).filter(|options| !options.trim().is_empty());
+    }
+
+    let mut kept = Vec::new();
+    let mut flags = options.split_whitespace();
+    while let Some(flag) = flags.next() {
+        if !is_debugger_flag(flag) {
+            kept.push(flag);
+            continue;
+        }
+        println!("[wc-tools] Not passing {flag} in NODE_OPTIONS to the language server");
+        if matches!(flag, "--inspect-port" | "--debug-port") {
+            flags.next();
+        }
+    }
+    (!kept.is_empty()).then(|| kept.join(" "))
+}
+
+fn check_installed_version(path: &str) -> Result<()> {
+    let output = zed::process::Command::new(path)
+        .arg("--version")
+        .output()
+        .map_err(|err| format!("failed to run Node.js at {path}: {err}"))?;
+    if output.status != Some(0) {
+        return Err(format!(
+            "Node.js at {path} exited with status {} when asked for --version",
+            output
+                .status
+                .map_or_else(|| "unknown".to_string(), |status| status.to_string())
+        ));
+    }
+    check_version(path, &String::from_utf8_lossy(&output.stdout))
+}
+
+/// Verifies that `reported`, the output of `node --version`, meets `MINIMUM_NODE_VERSION`.
+fn check_version(path: &str, reported: &str) -> Result<()> {
+    let reported = reported.trim();
+    let version = parse_version(reported).ok_or_else(|| {
+        format!("could not read the Node.js version reported by {path}: {reported:?}")
+    })?;
+    if version < MINIMUM_NODE_VERSION {
+        let (major, minor, patch) = MINIMUM_NODE_VERSION;
+        return Err(format!(
+            "Node.js at {path} is {reported}, but the language server needs v{major}.{minor}.{patch} or newer; update it or change the `node.path` setting under lsp.wc-language-server.settings"
+        ));
+    }
+    Ok(())
+}
+
+/// Parses `v20.11.1` (or `20.11`) into its numeric parts; missing parts count as zero.
+fn parse_version(version: &str) -> Option<(u64, u64, u64)> {
+    let version = version.trim().strip_prefix('v').unwrap_or(version.trim());
+    let mut parts = version
+        .split(['.', '-', '+'])
+        .take(3)
+        .map(|part| part.parse::<u64>());
+    let major = parts.next()?.ok()?;
+    let minor = parts.next().unwrap_or(Ok(0)).ok()?;
+    let patch = parts.next().unwrap_or(Ok(0)).ok()?;
+    Some((major, minor, patch))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_version_reads_node_output() {
+        assert_eq!(parse_version("v20.11.1\n"), Some((20, 11, 1)));
+        assert_eq!(parse_version("18.0"), Some((18, 0, 0)));
+        assert_eq!(parse_version("v22.0.0-nightly"), Some((22, 0, 0)));
+        assert_eq!(parse_version("node"), None);
+    }
+
+    #[test]
+    fn sanitize_env_strips_debugger_and_parent_process_vars() {
+        let env = vec![
+            ("PATH".to_string(), "/usr/bin".to_string()),
+            (
+                "NODE_OPTIONS".to_string(),
+                "--inspect-brk=9229 --max-old-space-size=4096 --inspect-port 9230 -r global-agent/bootstrap"
+                    .to_string(),
+            ),
+            ("NODE_CHANNEL_FD".to_string(), "3".to_string()),
+            ("HTTPS_PROXY".to_string(), "http://proxy:8080".to_string()),
+        ];
+
+        assert_eq!(
+            sanitize_env(env),
+            [
+                ("PATH".to_string(), "/usr/bin".to_string()),
+                (
+                    "NODE_OPTIONS".to_string(),
+                    "--max-old-space-size=4096 -r global-agent/bootstrap".to_string()
+                ),
+                ("HTTPS_PROXY".to_string(), "http://proxy:8080".to_string()),
+            ]
+        );
+        assert_eq!(
+            sanitize_env(vec![("NODE_OPTIONS".to_string(), "--inspect".to_string())]),
+            []
+        );
+    }
+
+    #[test]
+    fn check_version_enforces_minimum() {
+        assert!(check_version("/usr/bin/node", "v18.0.0").is_ok());
+        assert!(check_version("/usr/bin/node", "v22.3.0").is_ok());
+
+        let err = check_version("/usr/bin/node", "v16.20.2").unwrap_err();
+        assert!(err.contains("v16.20.2"));
+        assert!(err.contains("v18.0.0 or newer"));
+        assert!(check_version("/usr/bin/node", "").is_err());
+    }
+}
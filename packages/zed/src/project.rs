@@ -0,0 +1,278 @@
+use std::path::{Path, PathBuf};
+use zed_extension_api::{self as zed, serde_json::Value};
+
+use crate::SERVER_PACKAGE_NAME;
+
+const SERVER_BIN_NAME: &str = "wc-language-server";
+/// The single-file server bundle every published package ships, relative to the package root.
+const SERVER_SCRIPT_PATH: &str = "bin/wc-language-server.js";
+/// The CLI, which only web-components projects depend on.
+const CLI_PACKAGE_NAME: &str = "@wc-toolkit/wctools";
+/// Language-server config files, any of which marks a web-components project.
+pub const CONFIG_FILE_NAMES: [&str; 5] = [
+    "wc.config.js",
+    "wc.config.ts",
+    "wc.config.mjs",
+    "wc.config.cjs",
+    "wc.config.json",
+];
+const MANIFEST_FILE_NAME: &str = "custom-elements.json";
+const DEPENDENCY_FIELDS: [&str; 4] = [
+    "dependencies",
+    "devDependencies",
+    "optionalDependencies",
+    "peerDependencies",
+];
+
+/// Returns the language-server entry point installed in the worktree's `node_modules`,
+/// provided the worktree's `package.json` declares `@wc-toolkit/language-server` and the
+/// entry point exists.
+pub fn local_server_script(worktree: &zed::Worktree) -> Option<PathBuf> {
+    let package_json = read_json(worktree, "package.json")?;
+    if !declares_dependency(&package_json, SERVER_PACKAGE_NAME) {
+        return None;
+    }
+
+    let package_dir = format!("node_modules/{SERVER_PACKAGE_NAME}");
+    let installed = read_json(worktree, &format!("{package_dir}/package.json"))?;
+    let script = find_server_script(&installed, |path| {
+        worktree
+            .read_text_file(&format!("{package_dir}/{path}"))
+            .is_ok()
+    })?;
+
+    Some(
+        Path::new(&worktree.root_path())
+            .join(package_dir)
+            .join(script),
+    )
+}
+
+/// The package-relative server script of an installed `@wc-toolkit/language-server`: the
+/// bundled `bin/wc-language-server.js`, or else the package's `bin` entry, whichever `exists`
+/// first. Published packages map `bin` to an extensionless file the build never produces, so
+/// the mapping alone cannot be trusted.
+fn find_server_script(installed: &Value, exists: impl Fn(&str) -> bool) -> Option<String> {
+    let bin =
+        bin_entry(installed, SERVER_BIN_NAME).map(|bin| bin.trim_start_matches("./").to_string());
+    [Some(SERVER_SCRIPT_PATH.to_string()), bin]
+        .into_iter()
+        .flatten()
+        .find(|path| exists(path))
+}
+
+/// The language-server version range the worktree's dependency on
+/// `@wc-toolkit/language-server` declares.
+pub fn declared_server_range(worktree: &zed::Worktree) -> Option<String> {
+    let package_json = read_json(worktree, "package.json")?;
+    dependency_range(&package_json, SERVER_PACKAGE_NAME)
+}
+
+/// Why the worktree looks like a web-components project, or `None` if it does not.
+pub fn web_components_marker(worktree: &zed::Worktree) -> Option<String> {
+    find_web_components_marker(|path| worktree.read_text_file(path).ok())
+}
+
+/// Looks for a config file, a root `custom-elements.json`, a `customElements` field in
+/// `package.json`, a dependency on the server or CLI, or a dependency that publishes a
+/// manifest, reading worktree files through `read`.
+fn find_web_components_marker(read: impl Fn(&str) -> Option<String>) -> Option<String> {
+    if let Some(config) = CONFIG_FILE_NAMES.iter().find(|name| read(name).is_some()) {
+        return Some(format!("found {config}"));
+    }
+    if read(MANIFEST_FILE_NAME).is_some() {
+        return Some(format!("found {MANIFEST_FILE_NAME}"));
+    }
+
+    let parse = |path: &str| -> Option<Value> { zed::serde_json::from_str(&read(path)?).ok() };
+    let package_json = parse("package.json")?;
+    if package_json.get("customElements").is_some() {
+        return Some("package.json declares customElements".to_string());
+    }
+    if let Some(package) = [SERVER_PACKAGE_NAME, CLI_PACKAGE_NAME]
+        .into_iter()
+        .find(|package| declares_dependency(&package_json, package))
+    {
+        return Some(format!("package.json depends on {package}"));
+    }
+
+    // Like the server, only runtime dependencies are scanned for manifests.
+    let dependencies = package_json.get("dependencies")?.as_object()?;
+    dependencies.keys().find_map(|dependency| {
+        parse(&format!("node_modules/{dependency}/package.json"))?
+            .get("customElements")
+            .map(|_| format!("dependency {dependency} publishes custom elements"))
+    })
+}
+
+fn read_json(worktree: &zed::Worktree, path: &str) -> Option<Value> {
+    let contents = worktree.read_text_file(path).ok()?;
+    zed::serde_json::from_str(&contents).ok()
+}
+
+/// Whether any dependency field of `package_json` lists `name`.
+pub fn declares_dependency(package_json: &Value, name: &str) -> bool {
+    DEPENDENCY_FIELDS.iter().any(|field| {
+        package_json
+            .get(field)
+            .and_then(|deps| deps.get(name))
+            .is_some()
+    })
+}
+
+/// The version range `package_json` declares for `name`, from the first dependency field
+/// that lists it.
+pub fn dependency_range(package_json: &Value, name: &str) -> Option<String> {
+    DEPENDENCY_FIELDS.iter().find_map(|field| {
+        package_json
+            .get(field)?
+            .get(name)?
+            .as_str()
+            .map(str::to_owned)
+    })
+}
+
+/// Resolves a package's `bin` field, which is either a single path or a map of command names.
+pub fn bin_entry(package_json: &Value, bin_name: &str) -> Option<String> {
+    match package_json.get("bin")? {
+        Value::String(path) => Some(path.clone()),
+        Value::Object(bins) => bins
+            .get(bin_name)
+            .or_else(|| bins.values().next())
+            .and_then(Value::as_str)
+            .map(str::to_owned),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zed::serde_json::json;
+
+    #[test]
+    fn declares_dependency_checks_every_dependency_field() {
+        let dev = json!({ "devDependencies": { "@wc-toolkit/language-server": "^0.0.7" } });
+        let peer = json!({ "peerDependencies": { "@wc-toolkit/language-server": "*" } });
+        let other = json!({ "dependencies": { "lit": "^3.0.0" } });
+
+        assert!(declares_dependency(&dev, SERVER_PACKAGE_NAME));
+        assert!(declares_dependency(&peer, SERVER_PACKAGE_NAME));
+        assert!(!declares_dependency(&other, SERVER_PACKAGE_NAME));
+    }
+
+    fn marker_for(files: &[(&str, &str)]) -> Option<String> {
+        let files: std::collections::HashMap<_, _> = files.iter().copied().collect();
+        find_web_components_marker(|path| files.get(path).map(|contents| contents.to_string()))
+    }
+
+    #[test]
+    fn web_components_marker_detects_config_manifest_and_package_fields() {
+        assert_eq!(
+            marker_for(&[("wc.config.mjs", "export default {}")]).as_deref(),
+            Some("found wc.config.mjs")
+        );
+        assert_eq!(
+            marker_for(&[("custom-elements.json", "{}")]).as_deref(),
+            Some("found custom-elements.json")
+        );
+        assert_eq!(
+            marker_for(&[(
+                "package.json",
+                r#"{ "customElements": "dist/custom-elements.json" }"#
+            )])
+            .as_deref(),
+            Some("package.json declares customElements")
+        );
+        assert_eq!(
+            marker_for(&[(
+                "package.json",
+                r#"{ "devDependencies": { "@wc-toolkit/wctools": "^0.0.19" } }"#
+            )])
+            .as_deref(),
+            Some("package.json depends on @wc-toolkit/wctools")
+        );
+        assert_eq!(
+            marker_for(&[
+                (
+                    "package.json",
+                    r#"{ "dependencies": { "lit": "^3.0.0", "@shoelace-style/shoelace": "^2.0.0" } }"#
+                ),
+                (
+                    "node_modules/@shoelace-style/shoelace/package.json",
+                    r#"{ "customElements": "dist/custom-elements.json" }"#
+                ),
+            ])
+            .as_deref(),
+            Some("dependency @shoelace-style/shoelace publishes custom elements")
+        );
+    }
+
+    #[test]
+    fn web_components_marker_ignores_other_projects() {
+        assert_eq!(marker_for(&[]), None);
+        assert_eq!(marker_for(&[("Cargo.toml", "[package]")]), None);
+        assert_eq!(
+            marker_for(&[(
+                "package.json",
+                r#"{ "dependencies": { "react": "^18.0.0" } }"#
+            )]),
+            None
+        );
+    }
+
+    #[test]
+    fn dependency_range_reads_the_first_declaring_field() {
+        let package_json = json!({
+            "dependencies": { "lit": "^3.0.0" },
+            "devDependencies": { "@wc-toolkit/language-server": "~0.0.9" },
+            "peerDependencies": { "@wc-toolkit/language-server": "*" }
+        });
+
+        assert_eq!(
+            dependency_range(&package_json, SERVER_PACKAGE_NAME).as_deref(),
+            Some("~0.0.9")
+        );
+        assert_eq!(dependency_range(&package_json, CLI_PACKAGE_NAME), None);
+    }
+
+    #[test]
+    fn find_server_script_prefers_the_bundle_and_requires_the_file() {
+        let installed = json!({ "bin": { "wc-language-server": "./bin/wc-language-server" } });
+        let has = |files: &'static [&'static str]| move |path: &str| files.contains(&path);
+
+        assert_eq!(
+            find_server_script(
+                &installed,
+                has(&["bin/wc-language-server.js", "bin/wc-language-server"])
+            )
+            .as_deref(),
+            Some("bin/wc-language-server.js")
+        );
+        assert_eq!(
+            find_server_script(&installed, has(&["bin/wc-language-server"])).as_deref(),
+            Some("bin/wc-language-server")
+        );
+        assert_eq!(find_server_script(&installed, has(&[])), None);
+        assert_eq!(
+            find_server_script(&json!({}), has(&["bin/wc-language-server.js"])).as_deref(),
+            Some("bin/wc-language-server.js")
+        );
+    }
+
+    #[test]
+    fn bin_entry_supports_string_and_map_forms() {
+        let map = json!({ "bin": { "wc-language-server": "./bin/wc-language-server" } });
+        let string = json!({ "bin": "bin/server.js" });
+
+        assert_eq!(
+            bin_entry(&map, SERVER_BIN_NAME).as_deref(),
+            Some("./bin/wc-language-server")
+        );
+        assert_eq!(
+            bin_entry(&string, SERVER_BIN_NAME).as_deref(),
+            Some("bin/server.js")
+        );
+        assert_eq!(bin_entry(&json!({}), SERVER_BIN_NAME), None);
+    }
+}
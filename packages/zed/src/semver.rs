@@ -0,0 +1,439 @@
+use std::{cmp::Ordering, fmt};
+
+/// A semantic version (`1.2.3`, `1.2.3-rc.1`); build metadata is ignored.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Version {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+    pub pre: Vec<String>,
+}
+
+impl Version {
+    /// Parses a version with an optional leading `v`.
+    pub fn parse(version: &str) -> Option<Self> {
+        let version = version.trim();
+        let version = version.strip_prefix('v').unwrap_or(version);
+        let version = version.split('+').next()?;
+        let (core, pre) = match version.split_once('-') {
+            Some((core, pre)) => (core, Some(pre)),
+            None => (version, None),
+        };
+
+        let mut parts = core.split('.').map(|part| part.parse::<u64>().ok());
+        let (major, minor, patch) = (parts.next()??, parts.next()??, parts.next()??);
+        if parts.next().is_some() {
+            return None;
+        }
+
+        let pre = match pre {
+            Some(pre) => {
+                let identifiers: Vec<_> = pre.split('.').map(str::to_owned).collect();
+                if identifiers.iter().any(String::is_empty) {
+                    return None;
+                }
+                identifiers
+            }
+            None => Vec::new(),
+        };
+        Some(Self {
+            major,
+            minor,
+            patch,
+            pre,
+        })
+    }
+
+    pub fn core(&self) -> (u64, u64, u64) {
+        (self.major, self.minor, self.patch)
+    }
+}
+
+impl Ord for Version {
+    /// Semver precedence: a pre-release sorts before its release, and pre-release
+    /// identifiers compare numerically when both are numbers.
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.core().cmp(&other.core()).then_with(|| {
+            match (self.pre.is_empty(), other.pre.is_empty()) {
+                (true, true) => Ordering::Equal,
+                (true, false) => Ordering::Greater,
+                (false, true) => Ordering::Less,
+                (false, false) => {
+                    for (left, right) in self.pre.iter().zip(&other.pre) {
+                        let ordering = match (left.parse::<u64>(), right.parse::<u64>()) {
+                            (Ok(left), Ok(right)) => left.cmp(&right),
+                            (Ok(_), Err(_)) => Ordering::Less,
+                            (Err(_), Ok(_)) => Ordering::Greater,
+                            (Err(_), Err(_)) => left.cmp(right),
+                        };
+                        if ordering != Ordering::Equal {
+                            return ordering;
+                        }
+                    }
+                    self.pre.len().cmp(&other.pre.len())
+                }
+            }
+        })
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)?;
+        if !self.pre.is_empty() {
+            write!(f, "-{}", self.pre.join("."))?;
+        }
+        Ok(())
+    }
+}
+
+/// Versions from `min` (inclusive) up to, but excluding, `max` and its pre-releases.
+#[derive(Debug, Clone, Copy)]
+pub struct VersionRange {
+    min: (u64, u64, u64),
+    max: (u64, u64, u64),
+}
+
+impl VersionRange {
+    pub const fn new(min: (u64, u64, u64), max: (u64, u64, u64)) -> Self {
+        Self { min, max }
+    }
+
+    pub fn contains(&self, version: &Version) -> bool {
+        version.core() >= self.min && version.core() < self.max
+    }
+}
+
+impl fmt::Display for VersionRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (min, max) = (self.min, self.max);
+        write!(
+            f,
+            ">={}.{}.{} <{}.{}.{}",
+            min.0, min.1, min.2, max.0, max.1, max.2
+        )
+    }
+}
+
+/// An npm-style version range such as `^0.0.7`, `~1.2`, `>=0.0.7 <0.1.0`, `1.x` or
+/// `0.0.7 || 0.0.9`. Like npm, pre-releases only match a comparator that names a pre-release
+/// of the same version.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct VersionReq {
+    alternatives: Vec<Vec<Comparator>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Comparator {
+    op: Op,
+    version: Version,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Op {
+    Exact,
+    Greater,
+    GreaterEq,
+    Less,
+    LessEq,
+}
+
+/// A version where trailing parts may be missing or wildcards (`1`, `1.2`, `1.x`, `*`).
+struct Partial {
+    major: Option<u64>,
+    minor: Option<u64>,
+    patch: Option<u64>,
+    pre: Vec<String>,
+}
+
+impl Partial {
+    fn parse(version: &str) -> Option<Self> {
+        let version = version.trim().trim_start_matches(['v', '=']);
+        let version = version.split('+').next()?;
+        let (core, pre) = match version.split_once('-') {
+            Some((core, pre)) => (core, pre.split('.').map(str::to_owned).collect()),
+            None => (version, Vec::new()),
+        };
+        let mut parts = core.split('.').map(|part| match part {
+            "x" | "X" | "*" => Some(None),
+            part => part.parse::<u64>().ok().map(Some),
+        });
+        let major = parts.next().unwrap_or(Some(None))?;
+        let minor = parts.next().unwrap_or(Some(None))?;
+        let patch = parts.next().unwrap_or(Some(None))?;
+        if parts.next().is_some() {
+            return None;
+        }
+        Some(Self {
+            major,
+            minor,
+            patch,
+            pre,
+        })
+    }
+
+    fn floor(&self) -> Version {
+        Version {
+            major: self.major.unwrap_or(0),
+            minor: self.minor.unwrap_or(0),
+            patch: self.patch.unwrap_or(0),
+            pre: self.pre.clone(),
+        }
+    }
+
+    /// The first version past everything this partial version covers, if it is bounded.
+    fn next_after(&self) -> Option<Version> {
+        let release = |major, minor, patch| Version {
+            major,
+            minor,
+            patch,
+            pre: Vec::new(),
+        };
+        match (self.major, self.minor, self.patch) {
+            (Some(major), None, _) => Some(release(major + 1, 0, 0)),
+            (Some(major), Some(minor), None) => Some(release(major, minor + 1, 0)),
+            _ => None,
+        }
+    }
+}
+
+impl Comparator {
+    fn new(op: Op, version: Version) -> Self {
+        Self { op, version }
+    }
+
+    fn matches(&self, version: &Version) -> bool {
+        match self.op {
+            Op::Exact => version == &self.version,
+            Op::Greater => version > &self.version,
+            Op::GreaterEq => version >= &self.version,
+            Op::Less => version < &self.version,
+            Op::LessEq => version <= &self.version,
+        }
+    }
+}
+
+impl VersionReq {
+    /// Parses a dependency specifier. `workspace:` prefixes are ignored; `*`, `x`, `latest`
+    /// and an empty string match any release. Git URLs, file paths and other non-semver
+    /// specifiers return `None`.
+    pub fn parse(requirement: &str) -> Option<Self> {
+        let requirement = requirement.trim();
+        let requirement = requirement
+            .strip_prefix("workspace:")
+            .unwrap_or(requirement);
+        let alternatives = requirement
+            .split("||")
+            .map(Self::parse_set)
+            .collect::<Option<Vec<_>>>()?;
+        Some(Self { alternatives })
+    }
+
+    /// Parses space-separated comparators that must all match.
+    fn parse_set(set: &str) -> Option<Vec<Comparator>> {
+        let set = set.trim();
+        if matches!(set, "" | "*" | "x" | "X" | "latest") {
+            return Some(Vec::new());
+        }
+        if let Some((low, high)) = set.split_once(" - ") {
+            let (low, high) = (Partial::parse(low)?, Partial::parse(high)?);
+            let mut comparators = vec![Comparator::new(Op::GreaterEq, low.floor())];
+            comparators.push(match high.next_after() {
+                Some(next) => Comparator::new(Op::Less, next),
+                None => Comparator::new(Op::LessEq, high.floor()),
+            });
+            return Some(comparators);
+        }
+
+        // Allow `>= 1.2.3` as well as `>=1.2.3`.
+        let mut tokens = Vec::new();
+        let mut pending_op = String::new();
+        for token in set.split_whitespace() {
+            if token
+                .chars()
+                .all(|ch| matches!(ch, '<' | '>' | '=' | '^' | '~'))
+            {
+                pending_op.push_str(token);
+            } else {
+                tokens.push(format!("{pending_op}{token}"));
+                pending_op.clear();
+            }
+        }
+        if !pending_op.is_empty() {
+            return None;
+        }
+
+        let mut comparators = Vec::new();
+        for token in tokens {
+            comparators.extend(Self::parse_comparator(&token)?);
+        }
+        Some(comparators)
+    }
+
+    fn parse_comparator(token: &str) -> Option<Vec<Comparator>> {
+        let op_len = token
+            .find(|ch: char| !matches!(ch, '<' | '>' | '=' | '^' | '~'))
+            .unwrap_or(token.len());
+        let (op, version) = token.split_at(op_len);
+        let partial = Partial::parse(version)?;
+        let floor = partial.floor();
+        if partial.major.is_none() {
+            // `*`, `>=*` and friends match everything; `<*` and `>*` match nothing sensible.
+            return matches!(op, "" | "=" | ">=" | "<=" | "^" | "~").then(Vec::new);
+        }
+
+        let range = |upper: Option<Version>| {
+            let mut comparators = vec![Comparator::new(Op::GreaterEq, floor.clone())];
+            comparators.extend(upper.map(|upper| Comparator::new(Op::Less, upper)));
+            comparators
+        };
+        let release = |major, minor, patch| Version {
+            major,
+            minor,
+            patch,
+            pre: Vec::new(),
+        };
+        let (major, minor, patch) = (partial.major?, partial.minor, partial.patch);
+        Some(match op {
+            "" | "=" => match partial.next_after() {
+                Some(upper) => range(Some(upper)),
+                None => vec![Comparator::new(Op::Exact, floor)],
+            },
+            "^" => {
+                let upper = match (major, minor, patch) {
+                    (0, Some(0), Some(patch)) => release(0, 0, patch + 1),
+                    (0, Some(minor), _) => release(0, minor + 1, 0),
+                    (major, _, _) => release(major + 1, 0, 0),
+                };
+                range(Some(upper))
+            }
+            "~" | "~>" => {
+                let upper = match minor {
+                    Some(minor) => release(major, minor + 1, 0),
+                    None => release(major + 1, 0, 0),
+                };
+                range(Some(upper))
+            }
+            ">=" => vec![Comparator::new(Op::GreaterEq, floor)],
+            ">" => match partial.next_after() {
+                Some(next) => vec![Comparator::new(Op::GreaterEq, next)],
+                None => vec![Comparator::new(Op::Greater, floor)],
+            },
+            "<" => vec![Comparator::new(Op::Less, floor)],
+            "<=" => match partial.next_after() {
+                Some(next) => vec![Comparator::new(Op::Less, next)],
+                None => vec![Comparator::new(Op::LessEq, floor)],
+            },
+            _ => return None,
+        })
+    }
+
+    pub fn matches(&self, version: &Version) -> bool {
+        self.alternatives.iter().any(|comparators| {
+            comparators
+                .iter()
+                .all(|comparator| comparator.matches(version))
+                && (version.pre.is_empty()
+                    || comparators.iter().any(|comparator| {
+                        !comparator.version.pre.is_empty()
+                            && comparator.version.core() == version.core()
+                    }))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(version: &str) -> Version {
+        Version::parse(version).unwrap()
+    }
+
+    #[test]
+    fn parse_accepts_prefixes_and_pre_releases() {
+        assert_eq!(version("v1.2.3").core(), (1, 2, 3));
+        assert_eq!(version("0.0.9-rc.1").pre, ["rc", "1"]);
+        assert_eq!(version("1.0.0+build.5"), version("1.0.0"));
+        assert_eq!(version("0.0.9-rc.1").to_string(), "0.0.9-rc.1");
+        assert_eq!(Version::parse("1.2"), None);
+        assert_eq!(Version::parse("1.2.3.4"), None);
+        assert_eq!(Version::parse("1.2.3-"), None);
+        assert_eq!(Version::parse("latest"), None);
+    }
+
+    #[test]
+    fn ordering_follows_semver_precedence() {
+        let ordered = [
+            "0.0.9-alpha",
+            "0.0.9-alpha.1",
+            "0.0.9-alpha.beta",
+            "0.0.9-beta.2",
+            "0.0.9-beta.11",
+            "0.0.9-rc.1",
+            "0.0.9",
+            "0.0.10",
+            "0.1.0",
+        ];
+        for pair in ordered.windows(2) {
+            assert!(version(pair[0]) < version(pair[1]), "{pair:?}");
+        }
+    }
+
+    fn matches(requirement: &str, candidate: &str) -> bool {
+        VersionReq::parse(requirement)
+            .unwrap_or_else(|| panic!("failed to parse {requirement}"))
+            .matches(&version(candidate))
+    }
+
+    #[test]
+    fn version_req_supports_npm_operators() {
+        assert!(matches("^0.0.7", "0.0.7"));
+        assert!(!matches("^0.0.7", "0.0.8"));
+        assert!(matches("^0.2.1", "0.2.9"));
+        assert!(!matches("^0.2.1", "0.3.0"));
+        assert!(matches("^1.2.3", "1.9.0"));
+        assert!(!matches("^1.2.3", "2.0.0"));
+        assert!(matches("~0.0.7", "0.0.19"));
+        assert!(!matches("~0.0.7", "0.1.0"));
+        assert!(matches("0.0.7", "0.0.7"));
+        assert!(!matches("0.0.7", "0.0.8"));
+        assert!(matches("0.0.x", "0.0.12"));
+        assert!(matches(">= 0.0.7 < 0.1.0", "0.0.9"));
+        assert!(!matches(">=0.0.7 <0.1.0", "0.1.0"));
+        assert!(matches("0.0.5 - 0.0.8", "0.0.8"));
+        assert!(matches("0.0.6 || ^0.0.9", "0.0.9"));
+        assert!(matches("workspace:*", "3.0.0"));
+        assert!(matches("latest", "0.0.1"));
+        assert_eq!(
+            VersionReq::parse("github:wc-toolkit/wc-language-server"),
+            None
+        );
+        assert_eq!(VersionReq::parse("file:../language-server"), None);
+    }
+
+    #[test]
+    fn version_req_only_matches_named_pre_releases() {
+        assert!(!matches("^0.0.9", "0.0.10-rc.1"));
+        assert!(!matches("*", "0.0.10-rc.1"));
+        assert!(matches("^0.0.10-rc.1", "0.0.10-rc.2"));
+        assert!(!matches("^0.0.10-rc.1", "0.0.11-rc.1"));
+    }
+
+    #[test]
+    fn range_excludes_pre_releases_of_the_upper_bound() {
+        let range = VersionRange::new((0, 0, 7), (1, 0, 0));
+        assert!(range.contains(&version("0.0.7")));
+        assert!(range.contains(&version("0.9.12-rc.1")));
+        assert!(!range.contains(&version("0.0.6")));
+        assert!(!range.contains(&version("1.0.0-rc.1")));
+        assert!(!range.contains(&version("1.0.0")));
+        assert_eq!(range.to_string(), ">=0.0.7 <1.0.0");
+    }
+}
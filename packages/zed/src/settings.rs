@@ -0,0 +1,345 @@
+use serde::Deserialize;
+use std::time::Duration;
+use zed_extension_api::serde_json;
+
+use crate::source::{ReleaseSource, release_tag};
+
+/// Extension options read from `lsp.wc-language-server.settings` in Zed's settings.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ExtensionSettings {
+    /// Exact language-server release to install instead of following the latest one.
+    pub version: Option<String>,
+    /// Which releases are considered when looking for updates.
+    pub channel: ReleaseChannel,
+    /// Minimum number of hours between release checks; `0` checks on every start.
+    pub update_check_interval_hours: Option<u64>,
+    /// Alternative to GitHub for environments without access to github.com.
+    pub release_source: Option<ReleaseSourceSettings>,
+    /// How many downloaded server versions to keep for rolling back.
+    pub keep_versions: Option<usize>,
+    /// Node.js used to run the JS server.
+    pub node: NodeSettings,
+    /// Whether to run the native executable, the JS bundle, or pick automatically.
+    pub runtime: RuntimeMode,
+    /// Start the server normally, or the JS server under the Node.js inspector.
+    pub launch_mode: LaunchMode,
+    /// Port the Node.js inspector listens on in the debug launch modes.
+    pub inspect_port: Option<u16>,
+    /// Start the server even in worktrees that show no sign of using web components.
+    pub force_enable: bool,
+}
+
+/// Node.js executable and flags for the JS server, e.g. `--max-old-space-size=8192`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct NodeSettings {
+    pub path: Option<String>,
+    pub arguments: Vec<String>,
+}
+
+/// A release mirror URL or a local directory of pre-downloaded assets.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ReleaseSourceSettings {
+    pub url: Option<String>,
+    pub path: Option<String>,
+}
+
+/// Which build of the managed language server to run.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuntimeMode {
+    /// The native executable, falling back to the JS bundle when it is missing or broken.
+    #[default]
+    Auto,
+    /// Only the native executable; failures are reported instead of falling back.
+    Native,
+    /// Only the JS bundle, run with Node.js.
+    Node,
+}
+
+/// How the language server process is started.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LaunchMode {
+    #[default]
+    Normal,
+    /// The JS server with the inspector listening (`--inspect`).
+    Debug,
+    /// The JS server paused on its first line until a debugger attaches (`--inspect-brk`).
+    DebugBrk,
+}
+
+impl LaunchMode {
+    /// The Node.js flag that starts the inspector, for the debug modes.
+    pub fn inspect_flag(self) -> Option<&'static str> {
+        match self {
+            Self::Normal => None,
+            Self::Debug => Some("--inspect"),
+            Self::DebugBrk => Some("--inspect-brk"),
+        }
+    }
+}
+
+/// Update channel for the managed language-server download.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReleaseChannel {
+    #[default]
+    Stable,
+    Prerelease,
+}
+
+impl ReleaseChannel {
+    pub fn includes_pre_releases(self) -> bool {
+        matches!(self, Self::Prerelease)
+    }
+
+    /// Directory, relative to the extension root, that caches this channel's server and version
+    /// marker. Kept apart from the bundled server in `server/bin`, which cleanup must not touch.
+    pub fn server_dir(self) -> &'static str {
+        match self {
+            Self::Stable => "server/managed/bin",
+            Self::Prerelease => "server/managed/prerelease/bin",
+        }
+    }
+
+    /// Where extension builds before `server_dir` cached this channel's server. The stable
+    /// directory doubles as the bundled server's.
+    pub fn legacy_server_dir(self) -> &'static str {
+        match self {
+            Self::Stable => "server/bin",
+            Self::Prerelease => "server/prerelease/bin",
+        }
+    }
+}
+
+impl ExtensionSettings {
+    /// Every key the extension reads from `lsp.wc-language-server.settings`.
+    pub const KEYS: [&str; 10] = [
+        "version",
+        "channel",
+        "updateCheckIntervalHours",
+        "releaseSource",
+        "keepVersions",
+        "node",
+        "runtime",
+        "launchMode",
+        "inspectPort",
+        "forceEnable",
+    ];
+    const DEFAULT_UPDATE_CHECK_INTERVAL_HOURS: u64 = 24;
+    const DEFAULT_KEEP_VERSIONS: usize = 3;
+    /// Node.js's own default inspector port.
+    const DEFAULT_INSPECT_PORT: u16 = 9229;
+
+    /// Parses the extension options, ignoring any server settings that share the same object.
+    pub fn from_value(settings: Option<&serde_json::Value>) -> Self {
+        settings
+            .and_then(|value| match serde_json::from_value(value.clone()) {
+                Ok(settings) => Some(settings),
+                Err(err) => {
+                    println!("[wc-tools] Ignoring invalid extension settings: {err}");
+                    None
+                }
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn update_check_interval(&self) -> Duration {
+        let hours = self
+            .update_check_interval_hours
+            .unwrap_or(Self::DEFAULT_UPDATE_CHECK_INTERVAL_HOURS);
+        Duration::from_secs(hours.saturating_mul(60 * 60))
+    }
+
+    /// Number of installed versions to keep, never fewer than the one in use.
+    pub fn versions_to_keep(&self) -> usize {
+        self.keep_versions
+            .unwrap_or(Self::DEFAULT_KEEP_VERSIONS)
+            .max(1)
+    }
+
+    pub fn inspect_port(&self) -> u16 {
+        self.inspect_port.unwrap_or(Self::DEFAULT_INSPECT_PORT)
+    }
+
+    /// Where releases are resolved from. A local `path` takes precedence over a mirror `url`.
+    pub fn release_source(&self) -> ReleaseSource {
+        let Some(source) = &self.release_source else {
+            return ReleaseSource::GitHub;
+        };
+        let non_empty = |value: &Option<String>| {
+            value
+                .as_deref()
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .map(str::to_owned)
+        };
+
+        if let Some(path) = non_empty(&source.path) {
+            ReleaseSource::Directory(path.into())
+        } else if let Some(url) = non_empty(&source.url) {
+            ReleaseSource::Mirror(url)
+        } else {
+            ReleaseSource::GitHub
+        }
+    }
+
+    /// The GitHub release tag for the pinned `version`, if one is configured.
+    ///
+    /// Accepts a bare version (`0.0.7`, `v0.0.7`) or a full release tag
+    /// (`@wc-toolkit/language-server@0.0.7`).
+    pub fn pinned_release_tag(&self) -> Option<String> {
+        let version = self.version.as_deref()?.trim();
+        (!version.is_empty()).then(|| release_tag(version))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn pinned_release_tag_normalizes_versions() {
+        let tag_for = |version: &str| {
+            ExtensionSettings::from_value(Some(&json!({ "version": version }))).pinned_release_tag()
+        };
+
+        assert_eq!(
+            tag_for("0.0.7").as_deref(),
+            Some("@wc-toolkit/language-server@0.0.7")
+        );
+        assert_eq!(
+            tag_for("v0.0.7").as_deref(),
+            Some("@wc-toolkit/language-server@0.0.7")
+        );
+        assert_eq!(
+            tag_for("@wc-toolkit/language-server@0.0.7").as_deref(),
+            Some("@wc-toolkit/language-server@0.0.7")
+        );
+        assert_eq!(tag_for("  "), None);
+    }
+
+    #[test]
+    fn from_value_ignores_server_settings_and_invalid_values() {
+        let settings = ExtensionSettings::from_value(Some(&json!({
+            "version": "0.0.7",
+            "manifestSrc": "custom-elements.json",
+        })));
+        assert_eq!(settings.version.as_deref(), Some("0.0.7"));
+
+        let settings = ExtensionSettings::from_value(Some(&json!({ "version": 7 })));
+        assert_eq!(settings.version, None);
+        assert_eq!(ExtensionSettings::from_value(None).version, None);
+    }
+
+    #[test]
+    fn channel_defaults_to_stable_and_keeps_separate_caches() {
+        assert_eq!(
+            ExtensionSettings::from_value(None).channel,
+            ReleaseChannel::Stable
+        );
+
+        let settings = ExtensionSettings::from_value(Some(&json!({ "channel": "prerelease" })));
+        assert_eq!(settings.channel, ReleaseChannel::Prerelease);
+        assert!(settings.channel.includes_pre_releases());
+        assert!(!ReleaseChannel::Stable.includes_pre_releases());
+        assert_ne!(
+            ReleaseChannel::Stable.server_dir(),
+            ReleaseChannel::Prerelease.server_dir()
+        );
+    }
+
+    #[test]
+    fn release_source_prefers_local_directory() {
+        assert_eq!(
+            ExtensionSettings::from_value(None).release_source(),
+            ReleaseSource::GitHub
+        );
+
+        let mirror = ExtensionSettings::from_value(Some(&json!({
+            "releaseSource": { "url": "https://mirror.example.com/wc" }
+        })));
+        assert_eq!(
+            mirror.release_source(),
+            ReleaseSource::Mirror("https://mirror.example.com/wc".to_string())
+        );
+
+        let both = ExtensionSettings::from_value(Some(&json!({
+            "releaseSource": { "url": "https://mirror.example.com/wc", "path": "/opt/wc" }
+        })));
+        assert_eq!(
+            both.release_source(),
+            ReleaseSource::Directory("/opt/wc".into())
+        );
+    }
+
+    #[test]
+    fn node_settings_accept_path_and_flags() {
+        let settings = ExtensionSettings::from_value(Some(&json!({
+            "node": { "path": "/opt/node/bin/node", "arguments": ["--max-old-space-size=8192"] }
+        })));
+        assert_eq!(settings.node.path.as_deref(), Some("/opt/node/bin/node"));
+        assert_eq!(settings.node.arguments, ["--max-old-space-size=8192"]);
+        assert!(
+            ExtensionSettings::from_value(None)
+                .node
+                .arguments
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn runtime_defaults_to_auto() {
+        assert_eq!(
+            ExtensionSettings::from_value(None).runtime,
+            RuntimeMode::Auto
+        );
+        let settings = ExtensionSettings::from_value(Some(&json!({ "runtime": "node" })));
+        assert_eq!(settings.runtime, RuntimeMode::Node);
+    }
+
+    #[test]
+    fn launch_mode_defaults_to_normal_with_the_node_inspector_port() {
+        let settings = ExtensionSettings::from_value(None);
+        assert_eq!(settings.launch_mode, LaunchMode::Normal);
+        assert_eq!(settings.launch_mode.inspect_flag(), None);
+        assert_eq!(settings.inspect_port(), 9229);
+
+        let settings = ExtensionSettings::from_value(Some(&json!({
+            "launchMode": "debug-brk",
+            "inspectPort": 9339
+        })));
+        assert_eq!(settings.launch_mode.inspect_flag(), Some("--inspect-brk"));
+        assert_eq!(settings.inspect_port(), 9339);
+    }
+
+    #[test]
+    fn force_enable_defaults_to_off() {
+        assert!(!ExtensionSettings::from_value(None).force_enable);
+        let settings = ExtensionSettings::from_value(Some(&json!({ "forceEnable": true })));
+        assert!(settings.force_enable);
+    }
+
+    #[test]
+    fn versions_to_keep_defaults_to_three_and_keeps_at_least_one() {
+        assert_eq!(ExtensionSettings::from_value(None).versions_to_keep(), 3);
+        let settings = ExtensionSettings::from_value(Some(&json!({ "keepVersions": 0 })));
+        assert_eq!(settings.versions_to_keep(), 1);
+    }
+
+    #[test]
+    fn update_check_interval_defaults_to_a_day() {
+        assert_eq!(
+            ExtensionSettings::from_value(None).update_check_interval(),
+            Duration::from_secs(24 * 60 * 60)
+        );
+
+        let settings =
+            ExtensionSettings::from_value(Some(&json!({ "updateCheckIntervalHours": 0 })));
+        assert!(settings.update_check_interval().is_zero());
+    }
+}
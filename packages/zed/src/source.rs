@@ -0,0 +1,575 @@
+use serde::Deserialize;
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+use zed_extension_api::{self as zed, Result, http_client::HttpRequest, serde_json};
+
+use crate::{
+    COMPATIBLE_SERVER_VERSIONS, GITHUB_REPO, SERVER_PACKAGE_NAME,
+    semver::{Version, VersionReq},
+};
+
+/// Index file a mirror or local release directory serves at its root.
+pub const MIRROR_INDEX_NAME: &str = "index.json";
+/// Releases requested per page from the GitHub API, and how many pages to scan for a
+/// language-server release among the other packages' releases.
+const GITHUB_RELEASES_PER_PAGE: u32 = 100;
+const GITHUB_RELEASE_PAGES: u32 = 3;
+
+/// Where language-server releases are looked up and downloaded from.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ReleaseSource {
+    GitHub,
+    /// An HTTP(S) server laid out as `<url>/index.json` and `<url>/<version>/<asset>`.
+    Mirror(String),
+    /// A directory of pre-downloaded assets with the same layout as a mirror.
+    Directory(PathBuf),
+}
+
+/// Which release a managed install follows.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ReleaseSelection {
+    /// The newest compatible release on the update channel.
+    Latest,
+    /// The release tag set by the `version` setting.
+    Pinned(String),
+    /// The newest release matching the range the worktree's `package.json` declares.
+    Project {
+        declared: String,
+        requirement: VersionReq,
+    },
+}
+
+impl ReleaseSelection {
+    /// Whether a server installed from release `tag` satisfies this selection.
+    pub fn accepts(&self, tag: &str) -> bool {
+        match self {
+            Self::Latest => true,
+            Self::Pinned(pinned) => pinned == tag,
+            Self::Project { requirement, .. } => {
+                Version::parse(version_number(tag)).is_some_and(|version| {
+                    requirement.matches(&version) && COMPATIBLE_SERVER_VERSIONS.contains(&version)
+                })
+            }
+        }
+    }
+
+    /// Whether installs made for this selection become the server every other worktree
+    /// uses. A project's version only serves the worktrees that declare it.
+    pub fn updates_current(&self) -> bool {
+        !matches!(self, Self::Project { .. })
+    }
+}
+
+/// `index.json` served by a mirror, listing releases newest first.
+#[derive(Debug, Deserialize)]
+struct MirrorIndex {
+    releases: Vec<MirrorRelease>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MirrorRelease {
+    version: String,
+    #[serde(default)]
+    prerelease: bool,
+    assets: Vec<String>,
+}
+
+/// The fields of a GitHub REST API release that are needed to pick and download one.
+#[derive(Debug, Deserialize)]
+struct GitHubApiRelease {
+    tag_name: String,
+    #[serde(default)]
+    draft: bool,
+    #[serde(default)]
+    prerelease: bool,
+    #[serde(default)]
+    assets: Vec<GitHubApiAsset>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitHubApiAsset {
+    name: String,
+    browser_download_url: String,
+}
+
+/// Normalizes a bare version (`0.0.7`, `v0.0.7`) into the full release tag
+/// (`@wc-toolkit/language-server@0.0.7`); full tags are returned unchanged.
+pub fn release_tag(version: &str) -> String {
+    let version = version.trim();
+    if version.starts_with(SERVER_PACKAGE_NAME) {
+        return version.to_owned();
+    }
+    let version = version.strip_prefix('v').unwrap_or(version);
+    format!("{SERVER_PACKAGE_NAME}@{version}")
+}
+
+/// The plain version of a release tag (`@wc-toolkit/language-server@0.0.7` -> `0.0.7`),
+/// which is what the server prints for `--version`.
+pub fn version_number(tag: &str) -> &str {
+    let version = tag.trim();
+    let version = version
+        .strip_prefix(SERVER_PACKAGE_NAME)
+        .and_then(|rest| rest.strip_prefix('@'))
+        .unwrap_or(version);
+    version.strip_prefix('v').unwrap_or(version)
+}
+
+/// Picks the newest language-server release whose version `accepts`. Releases of the repo's
+/// other packages, releases without assets and, unless `include_pre_releases`, pre-releases
+/// are skipped. Each release is paired with whether it is a pre-release.
+fn select_release(
+    releases: Vec<(zed::GithubRelease, bool)>,
+    include_pre_releases: bool,
+    accepts: impl Fn(&Version) -> bool,
+) -> Option<zed::GithubRelease> {
+    let tag_prefix = format!("{SERVER_PACKAGE_NAME}@");
+    releases
+        .into_iter()
+        .filter(|(release, prerelease)| {
+            !release.assets.is_empty() && (include_pre_releases || !prerelease)
+        })
+        .filter_map(|(release, _)| {
+            let version = Version::parse(release.version.strip_prefix(&tag_prefix)?)?;
+            accepts(&version).then_some((version, release))
+        })
+        .max_by(|(left, _), (right, _)| left.cmp(right))
+        .map(|(_, release)| release)
+}
+
+/// Parses a page of `GET /repos/{repo}/releases`, dropping drafts.
+fn parse_github_releases(contents: &str) -> Result<Vec<(zed::GithubRelease, bool)>> {
+    let releases: Vec<GitHubApiRelease> = serde_json::from_str(contents).map_err(|err| {
+        // Errors such as rate limiting come back as an object with a `message`.
+        let message = serde_json::from_str::<serde_json::Value>(contents)
+            .ok()
+            .and_then(|value| value.get("message")?.as_str().map(str::to_owned));
+        match message {
+            Some(message) => format!("GitHub API error: {message}"),
+            None => format!("invalid GitHub releases response: {err}"),
+        }
+    })?;
+
+    Ok(releases
+        .into_iter()
+        .filter(|release| !release.draft)
+        .map(|release| {
+            (
+                zed::GithubRelease {
+                    version: release.tag_name,
+                    assets: release
+                        .assets
+                        .into_iter()
+                        .map(|asset| zed::GithubReleaseAsset {
+                            name: asset.name,
+                            download_url: asset.browser_download_url,
+                        })
+                        .collect(),
+                },
+                release.prerelease,
+            )
+        })
+        .collect())
+}
+
+impl ReleaseSource {
+    /// Human-readable name used in log and error messages.
+    pub fn describe(&self) -> String {
+        match self {
+            Self::GitHub => format!("the {GITHUB_REPO} GitHub releases"),
+            Self::Mirror(url) => format!("the release mirror at {url}"),
+            Self::Directory(path) => format!("the release directory {}", path.display()),
+        }
+    }
+
+    /// Whether assets can be unpacked while fetching. Local directories are copied as-is,
+    /// so only uncompressed assets are usable there.
+    pub fn supports_compressed_assets(&self) -> bool {
+        !matches!(self, Self::Directory(_))
+    }
+
+    /// The newest language-server release compatible with this extension.
+    pub fn latest_release(&self, include_pre_releases: bool) -> Result<zed::GithubRelease> {
+        self.newest_release(include_pre_releases, |version| {
+            COMPATIBLE_SERVER_VERSIONS.contains(version)
+        })?
+        .ok_or_else(|| {
+            format!(
+                "{} has no language server release compatible with this extension ({COMPATIBLE_SERVER_VERSIONS})",
+                self.describe()
+            )
+        })
+    }
+
+    /// The newest release that matches a range a project declared and is compatible with this
+    /// extension. Pre-releases only match ranges that name one.
+    pub fn matching_release(
+        &self,
+        declared: &str,
+        requirement: &VersionReq,
+    ) -> Result<zed::GithubRelease> {
+        self.newest_release(true, |version| {
+            requirement.matches(version) && COMPATIBLE_SERVER_VERSIONS.contains(version)
+        })?
+        .ok_or_else(|| {
+            format!(
+                "{} has no language server release that matches the project's {declared} and is compatible with this extension ({COMPATIBLE_SERVER_VERSIONS})",
+                self.describe()
+            )
+        })
+    }
+
+    fn newest_release(
+        &self,
+        include_pre_releases: bool,
+        accepts: impl Fn(&Version) -> bool,
+    ) -> Result<Option<zed::GithubRelease>> {
+        match self {
+            Self::GitHub => {
+                for page in 1..=GITHUB_RELEASE_PAGES {
+                    let releases = Self::github_releases(page)?;
+                    let last_page = releases.len() < GITHUB_RELEASES_PER_PAGE as usize;
+                    if let Some(release) = select_release(releases, include_pre_releases, &accepts)
+                    {
+                        return Ok(Some(release));
+                    }
+                    if last_page {
+                        break;
+                    }
+                }
+                Ok(None)
+            }
+            _ => Ok(select_release(
+                self.mirror_releases()?,
+                include_pre_releases,
+                accepts,
+            )),
+        }
+    }
+
+    pub fn release_by_tag(&self, tag: &str) -> Result<zed::GithubRelease> {
+        match self {
+            Self::GitHub => zed::github_release_by_tag_name(GITHUB_REPO, tag),
+            _ => self
+                .mirror_releases()?
+                .into_iter()
+                .map(|(release, _)| release)
+                .find(|release| release.version == tag)
+                .ok_or_else(|| format!("{} has no release {tag}", self.describe())),
+        }
+    }
+
+    /// Fetches `asset` to `path`, unpacking it according to `file_type`.
+    pub fn fetch(
+        &self,
+        asset: &zed::GithubReleaseAsset,
+        path: &Path,
+        file_type: zed::DownloadedFileType,
+    ) -> Result<()> {
+        match self {
+            Self::Directory(_) => {
+                if file_type != zed::DownloadedFileType::Uncompressed {
+                    return Err(format!(
+                        "cannot unpack {} from {}",
+                        asset.name,
+                        self.describe()
+                    ));
+                }
+                fs::copy(&asset.download_url, path)
+                    .map(|_| ())
+                    .map_err(|err| directory_error("copy", Path::new(&asset.download_url), err))
+            }
+            _ => zed::download_file(&asset.download_url, &path.to_string_lossy(), file_type),
+        }
+    }
+
+    /// Lists one page of the repository's releases, newest first, through the GitHub API.
+    fn github_releases(page: u32) -> Result<Vec<(zed::GithubRelease, bool)>> {
+        let url = format!(
+            "https://api.github.com/repos/{GITHUB_REPO}/releases?per_page={GITHUB_RELEASES_PER_PAGE}&page={page}"
+        );
+        let response = HttpRequest::builder()
+            .method(zed::http_client::HttpMethod::Get)
+            .url(&url)
+            .header("Accept", "application/vnd.github+json")
+            .header("User-Agent", "wc-language-server-zed")
+            .redirect_policy(zed::http_client::RedirectPolicy::FollowAll)
+            .build()?
+            .fetch()
+            .map_err(|err| format!("failed to fetch {url}: {err}"))?;
+        let contents = String::from_utf8(response.body)
+            .map_err(|err| format!("{url} is not valid UTF-8: {err}"))?;
+        parse_github_releases(&contents)
+    }
+
+    fn mirror_releases(&self) -> Result<Vec<(zed::GithubRelease, bool)>> {
+        let contents = match self {
+            Self::GitHub => return Ok(Vec::new()),
+            Self::Mirror(url) => {
+                let index_url = format!("{}/{MIRROR_INDEX_NAME}", url.trim_end_matches('/'));
+                let response = HttpRequest::builder()
+                    .method(zed::http_client::HttpMethod::Get)
+                    .url(&index_url)
+                    .redirect_policy(zed::http_client::RedirectPolicy::FollowAll)
+                    .build()?
+                    .fetch()
+                    .map_err(|err| format!("failed to fetch {index_url}: {err}"))?;
+                String::from_utf8(response.body)
+                    .map_err(|err| format!("{index_url} is not valid UTF-8: {err}"))?
+            }
+            Self::Directory(path) => {
+                let index_path = path.join(MIRROR_INDEX_NAME);
+                fs::read_to_string(&index_path)
+                    .map_err(|err| directory_error("read", &index_path, err))?
+            }
+        };
+        self.parse_index(&contents)
+    }
+
+    /// Turns a mirror index into releases whose asset URLs point into the mirror.
+    fn parse_index(&self, contents: &str) -> Result<Vec<(zed::GithubRelease, bool)>> {
+        let index: MirrorIndex = serde_json::from_str(contents)
+            .map_err(|err| format!("invalid {MIRROR_INDEX_NAME} in {}: {err}", self.describe()))?;
+
+        Ok(index
+            .releases
+            .into_iter()
+            .map(|release| {
+                let tag = release_tag(&release.version);
+                let version_dir = tag
+                    .strip_prefix(&format!("{SERVER_PACKAGE_NAME}@"))
+                    .unwrap_or(&tag)
+                    .to_owned();
+                let assets = release
+                    .assets
+                    .into_iter()
+                    .map(|name| zed::GithubReleaseAsset {
+                        download_url: self.asset_location(&version_dir, &name),
+                        name,
+                    })
+                    .collect();
+                (
+                    zed::GithubRelease {
+                        version: tag,
+                        assets,
+                    },
+                    release.prerelease,
+                )
+            })
+            .collect())
+    }
+
+    fn asset_location(&self, version_dir: &str, name: &str) -> String {
+        match self {
+            Self::Directory(path) => path
+                .join(version_dir)
+                .join(name)
+                .to_string_lossy()
+                .into_owned(),
+            Self::Mirror(url) => format!("{}/{version_dir}/{name}", url.trim_end_matches('/')),
+            Self::GitHub => String::new(),
+        }
+    }
+}
+
+/// Explains why a file in a release directory could not be read or copied.
+fn directory_error(action: &str, path: &Path, err: std::io::Error) -> String {
+    describe_directory_error(action, path, env::current_dir().ok().as_deref(), err)
+}
+
+/// The extension sandbox can only reach `work_dir`, the extension's own work directory, so
+/// for paths outside it the I/O error alone would not say what to change.
+fn describe_directory_error(
+    action: &str,
+    path: &Path,
+    work_dir: Option<&Path>,
+    err: std::io::Error,
+) -> String {
+    match work_dir {
+        Some(work_dir) if path.is_absolute() && !path.starts_with(work_dir) => format!(
+            "cannot {action} {}: Zed extensions can only read files inside their work directory ({}). Copy the release directory there and point `releaseSource.path` at it, or serve it with `releaseSource.url` instead",
+            path.display(),
+            work_dir.display()
+        ),
+        _ => format!("failed to {action} {}: {err}", path.display()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{semver::VersionRange, test_support::TempDir};
+
+    const INDEX: &str = r#"{
+        "releases": [
+            { "version": "0.0.9-rc.1", "prerelease": true, "assets": ["wc-language-server.js"] },
+            { "version": "0.0.8", "assets": ["SHA256SUMS", "wc-language-server.js"] }
+        ]
+    }"#;
+
+    #[test]
+    fn release_tag_normalizes_versions() {
+        assert_eq!(release_tag("0.0.7"), "@wc-toolkit/language-server@0.0.7");
+        assert_eq!(release_tag("v0.0.7"), "@wc-toolkit/language-server@0.0.7");
+        assert_eq!(
+            release_tag("@wc-toolkit/language-server@0.0.7"),
+            "@wc-toolkit/language-server@0.0.7"
+        );
+    }
+
+    #[test]
+    fn version_number_strips_tag_prefixes() {
+        assert_eq!(version_number("@wc-toolkit/language-server@0.0.7"), "0.0.7");
+        assert_eq!(version_number("v0.0.7"), "0.0.7");
+        assert_eq!(version_number("0.0.7\n"), "0.0.7");
+    }
+
+    #[test]
+    fn select_release_skips_other_packages_and_incompatible_versions() {
+        let releases = parse_github_releases(
+            r#"[
+                { "tag_name": "@wc-toolkit/vscode@0.1.1", "assets": [{ "name": "wc.vsix", "browser_download_url": "https://example.com/wc.vsix" }] },
+                { "tag_name": "@wc-toolkit/language-server@1.0.0", "assets": [{ "name": "wc-language-server.js", "browser_download_url": "https://example.com/1.0.0.js" }] },
+                { "tag_name": "@wc-toolkit/language-server@0.0.10-rc.1", "prerelease": true, "assets": [{ "name": "wc-language-server.js", "browser_download_url": "https://example.com/rc.js" }] },
+                { "tag_name": "@wc-toolkit/language-server@0.0.11", "draft": true, "assets": [{ "name": "wc-language-server.js", "browser_download_url": "https://example.com/draft.js" }] },
+                { "tag_name": "@wc-toolkit/language-server@0.0.8", "assets": [] },
+                { "tag_name": "@wc-toolkit/language-server@0.0.9", "assets": [{ "name": "wc-language-server.js", "browser_download_url": "https://example.com/0.0.9.js" }] },
+                { "tag_name": "@wc-toolkit/language-server@0.0.7", "assets": [{ "name": "wc-language-server.js", "browser_download_url": "https://example.com/0.0.7.js" }] }
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(releases.len(), 6);
+        let range = VersionRange::new((0, 0, 7), (1, 0, 0));
+        let compatible = |version: &Version| range.contains(version);
+
+        let stable = select_release(releases.clone(), false, compatible).unwrap();
+        assert_eq!(stable.version, "@wc-toolkit/language-server@0.0.9");
+        assert_eq!(
+            stable.assets[0].download_url,
+            "https://example.com/0.0.9.js"
+        );
+
+        let pre = select_release(releases.clone(), true, compatible).unwrap();
+        assert_eq!(pre.version, "@wc-toolkit/language-server@0.0.10-rc.1");
+
+        let requirement = VersionReq::parse("~0.0.7").unwrap();
+        let declared = select_release(releases, true, |version| requirement.matches(version));
+        assert_eq!(
+            declared.unwrap().version,
+            "@wc-toolkit/language-server@0.0.9"
+        );
+    }
+
+    #[test]
+    fn release_selection_accepts_matching_tags() {
+        let project = ReleaseSelection::Project {
+            declared: "^0.0.8".to_string(),
+            requirement: VersionReq::parse("^0.0.8").unwrap(),
+        };
+        assert!(project.accepts("@wc-toolkit/language-server@0.0.8"));
+        assert!(!project.accepts("@wc-toolkit/language-server@0.0.9"));
+        assert!(!project.updates_current());
+
+        // A project range never reaches past the releases this extension supports.
+        let any = ReleaseSelection::Project {
+            declared: "*".to_string(),
+            requirement: VersionReq::parse("*").unwrap(),
+        };
+        assert!(any.accepts("@wc-toolkit/language-server@0.0.9"));
+        assert!(!any.accepts("@wc-toolkit/language-server@1.0.0"));
+        assert!(!any.accepts("@wc-toolkit/language-server@0.0.1"));
+
+        let pinned = ReleaseSelection::Pinned(release_tag("0.0.7"));
+        assert!(pinned.accepts("@wc-toolkit/language-server@0.0.7"));
+        assert!(!pinned.accepts("@wc-toolkit/language-server@0.0.8"));
+        assert!(ReleaseSelection::Latest.accepts("@wc-toolkit/language-server@0.0.8"));
+    }
+
+    #[test]
+    fn parse_github_releases_reports_api_errors() {
+        let err = parse_github_releases(r#"{ "message": "API rate limit exceeded" }"#).unwrap_err();
+        assert_eq!(err, "GitHub API error: API rate limit exceeded");
+    }
+
+    #[test]
+    fn parse_index_builds_mirror_urls() {
+        let source = ReleaseSource::Mirror("https://mirror.example.com/wc/".to_string());
+        let releases = source.parse_index(INDEX).unwrap();
+
+        assert_eq!(releases.len(), 2);
+        let (rc, prerelease) = &releases[0];
+        assert!(*prerelease);
+        assert_eq!(rc.version, "@wc-toolkit/language-server@0.0.9-rc.1");
+
+        let (stable, prerelease) = &releases[1];
+        assert!(!*prerelease);
+        assert_eq!(stable.assets[1].name, "wc-language-server.js");
+        assert_eq!(
+            stable.assets[1].download_url,
+            "https://mirror.example.com/wc/0.0.8/wc-language-server.js"
+        );
+    }
+
+    #[test]
+    fn directory_errors_point_out_the_sandbox() {
+        let not_found = || std::io::Error::from(std::io::ErrorKind::NotFound);
+        let work_dir = Path::new("/zed/extensions/work/wc-language-server");
+
+        let outside = describe_directory_error(
+            "read",
+            Path::new("/opt/wc/index.json"),
+            Some(work_dir),
+            not_found(),
+        );
+        assert!(outside.starts_with(
+            "cannot read /opt/wc/index.json: Zed extensions can only read files inside their work directory (/zed/extensions/work/wc-language-server)"
+        ));
+
+        let inside = describe_directory_error(
+            "read",
+            &work_dir.join("releases/index.json"),
+            Some(work_dir),
+            not_found(),
+        );
+        assert!(inside.starts_with(
+            "failed to read /zed/extensions/work/wc-language-server/releases/index.json:"
+        ));
+    }
+
+    #[test]
+    fn directory_source_reads_index_from_disk() {
+        let root = TempDir::new("source");
+        fs::write(root.join(MIRROR_INDEX_NAME), INDEX).unwrap();
+        let source = ReleaseSource::Directory(root.to_path_buf());
+
+        let latest = source.latest_release(false).unwrap();
+        assert_eq!(latest.version, "@wc-toolkit/language-server@0.0.8");
+        assert_eq!(
+            PathBuf::from(&latest.assets[0].download_url),
+            root.join("0.0.8").join("SHA256SUMS")
+        );
+        assert_eq!(
+            source.latest_release(true).unwrap().version,
+            "@wc-toolkit/language-server@0.0.9-rc.1"
+        );
+        assert!(
+            source
+                .release_by_tag("@wc-toolkit/language-server@0.0.1")
+                .is_err()
+        );
+        assert!(!source.supports_compressed_assets());
+
+        let any = VersionReq::parse("*").unwrap();
+        assert_eq!(
+            source.matching_release("*", &any).unwrap().version,
+            "@wc-toolkit/language-server@0.0.8"
+        );
+        let err = source
+            .matching_release("^2.0.0", &VersionReq::parse("^2.0.0").unwrap())
+            .unwrap_err();
+        assert!(err.ends_with(
+            "has no language server release that matches the project's ^2.0.0 and is compatible with this extension (>=0.0.7 <1.0.0)"
+        ));
+    }
+}
@@ -0,0 +1,34 @@
+use std::{
+    env, fs,
+    ops::Deref,
+    path::{Path, PathBuf},
+    process,
+};
+
+/// An empty scratch directory under the system temp dir, removed with everything in it when
+/// dropped, so a failing assertion does not leave it behind.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    /// `name` must be unique among the crate's tests, which run in parallel.
+    pub fn new(name: &str) -> Self {
+        let path = env::temp_dir().join(format!("wc-{name}-{}", process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
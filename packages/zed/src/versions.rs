@@ -0,0 +1,549 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use zed_extension_api::Result;
+
+use crate::{archive, checksum, install};
+
+/// Environment variable telling the server where to write its "started OK" marker.
+pub const STARTED_MARKER_ENV: &str = "WC_LANGUAGE_SERVER_STARTED_MARKER";
+/// Launches in a row without a "started OK" marker before a version is rolled back.
+pub const MAX_FAILED_LAUNCHES: u32 = 3;
+/// How long a launch has to write its marker before it counts as failed. Launches still inside
+/// it, such as the same server starting for other worktrees, are left to settle later.
+const STARTUP_GRACE: Duration = Duration::from_secs(120);
+
+/// Installed versions, newest first, one per line.
+const HISTORY_NAME: &str = ".installed-versions";
+const KNOWN_GOOD_NAME: &str = ".known-good-version";
+const REJECTED_NAME: &str = ".rejected-versions";
+const LAUNCH_ATTEMPTS_NAME: &str = ".launch-attempts";
+/// One `<launch id>.pending` file per unsettled launch, next to the `<launch id>.started` marker
+/// that launch's server writes.
+const LAUNCHES_DIR_NAME: &str = ".launches";
+const PENDING_SUFFIX: &str = ".pending";
+const STARTED_SUFFIX: &str = ".started";
+/// Remembers whether a version's server writes started markers at all.
+const MARKER_SUPPORT_NAME: &str = ".writes-started-marker";
+/// Prefix shared by every server asset, native or JS, for any platform.
+const SERVER_ASSET_PREFIX: &str = "wc-language-server";
+/// Suffix of the directory a download is staged in until it has been verified.
+pub const STAGING_SUFFIX: &str = ".download";
+
+/// The outcome of recording one launch of a managed version.
+#[derive(Debug)]
+pub struct Launch {
+    /// The marker this launch's server writes once it has initialized; `None` when the version
+    /// does not write markers.
+    pub started_marker: Option<PathBuf>,
+    /// Launches in a row before this one that never wrote their marker.
+    pub failed_launches: u32,
+}
+
+/// Server versions installed side by side in a channel's server directory, each in its own
+/// directory, with the bookkeeping needed to roll back a release that keeps crashing.
+#[derive(Debug, Clone)]
+pub struct VersionStore {
+    server_dir: PathBuf,
+}
+
+impl VersionStore {
+    pub fn new(server_dir: &Path) -> Self {
+        Self {
+            server_dir: server_dir.to_path_buf(),
+        }
+    }
+
+    pub fn version_dir(&self, version: &str) -> PathBuf {
+        self.server_dir.join(archive::version_dir_name(version))
+    }
+
+    /// Versions whose directory still exists, most recently installed first.
+    pub fn installed(&self) -> Vec<String> {
+        read_lines(&self.server_dir.join(HISTORY_NAME))
+            .into_iter()
+            .filter(|version| self.version_dir(version).is_dir())
+            .collect()
+    }
+
+    /// Records `version` as the newest install and deletes the directories of versions beyond
+    /// the newest `keep`. The last known-good version is always kept so it can be rolled back to,
+    /// and so is `current`, the server other worktrees run when `version` is a project's own.
+    pub fn record_install(&self, version: &str, keep: usize, current: Option<&str>) -> Result<()> {
+        let mut history = vec![version.to_owned()];
+        history.extend(
+            read_lines(&self.server_dir.join(HISTORY_NAME))
+                .into_iter()
+                .filter(|installed| installed != version),
+        );
+
+        let known_good = self.known_good();
+        let (kept, removed): (Vec<_>, Vec<_>) =
+            history
+                .into_iter()
+                .enumerate()
+                .partition(|(index, installed)| {
+                    *index < keep.max(1)
+                        || Some(installed) == known_good.as_ref()
+                        || Some(installed.as_str()) == current
+                });
+        for (_, version) in removed {
+            let _ = fs::remove_dir_all(self.version_dir(&version));
+        }
+
+        let kept: Vec<_> = kept.into_iter().map(|(_, version)| version).collect();
+        install::write_atomically(&self.server_dir.join(HISTORY_NAME), &kept.join("\n"))
+    }
+
+    /// Which installed version `path` belongs to, if it lives in a version directory.
+    pub fn version_of(&self, path: &Path) -> Option<String> {
+        let relative = path.strip_prefix(&self.server_dir).ok()?;
+        let dir_name = relative.components().next()?.as_os_str().to_str()?;
+        self.installed()
+            .into_iter()
+            .find(|version| archive::version_dir_name(version) == dir_name)
+    }
+
+    /// Accounts for a launch of `version` from `executable` at `now` (since the Unix epoch).
+    /// Earlier launches are settled oldest first: one whose marker appeared marks the version as
+    /// known-good and resets the count, one still without it after `STARTUP_GRACE` counts as
+    /// failed. Servers built before the marker existed are never counted, since their silence
+    /// says nothing about whether they started.
+    pub fn record_launch(&self, version: &str, executable: &Path, now: Duration) -> Launch {
+        if !self.writes_started_marker(version, executable) {
+            return Launch {
+                started_marker: None,
+                failed_launches: 0,
+            };
+        }
+
+        let launches_dir = self.version_dir(version).join(LAUNCHES_DIR_NAME);
+        let launch_file = |id: u128, suffix: &str| launches_dir.join(format!("{id}{suffix}"));
+        let attempts_path = self.version_dir(version).join(LAUNCH_ATTEMPTS_NAME);
+        let mut failed_launches: u32 = fs::read_to_string(&attempts_path)
+            .ok()
+            .and_then(|attempts| attempts.trim().parse().ok())
+            .unwrap_or(0);
+
+        let mut pending: Vec<u128> = fs::read_dir(&launches_dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter_map(|entry| {
+                entry
+                    .file_name()
+                    .to_str()?
+                    .strip_suffix(PENDING_SUFFIX)?
+                    .parse()
+                    .ok()
+            })
+            .collect();
+        pending.sort_unstable();
+        for id in pending {
+            if launch_file(id, STARTED_SUFFIX).exists() {
+                failed_launches = 0;
+                self.mark_known_good(version);
+            } else if now.as_nanos().saturating_sub(id) >= STARTUP_GRACE.as_nanos() {
+                failed_launches += 1;
+            } else {
+                continue;
+            }
+            let _ = fs::remove_file(launch_file(id, PENDING_SUFFIX));
+            let _ = fs::remove_file(launch_file(id, STARTED_SUFFIX));
+        }
+        let _ = install::write_atomically(&attempts_path, &failed_launches.to_string());
+
+        let mut id = now.as_nanos();
+        while launch_file(id, PENDING_SUFFIX).exists() {
+            id += 1;
+        }
+        let started_marker = fs::create_dir_all(&launches_dir)
+            .and_then(|_| fs::write(launch_file(id, PENDING_SUFFIX), ""))
+            .ok()
+            .map(|_| launch_file(id, STARTED_SUFFIX));
+        Launch {
+            started_marker,
+            failed_launches,
+        }
+    }
+
+    /// Whether the server in `executable` writes the marker named in `STARTED_MARKER_ENV`,
+    /// judged by whether it mentions the variable. Remembered per version.
+    fn writes_started_marker(&self, version: &str, executable: &Path) -> bool {
+        let cache_path = self.version_dir(version).join(MARKER_SUPPORT_NAME);
+        if let Ok(cached) = fs::read_to_string(&cache_path) {
+            return cached.trim() == "true";
+        }
+        let Ok(contents) = fs::read(executable) else {
+            return false;
+        };
+        let supported = contents
+            .windows(STARTED_MARKER_ENV.len())
+            .any(|window| window == STARTED_MARKER_ENV.as_bytes());
+        let _ = install::write_atomically(&cache_path, &supported.to_string());
+        supported
+    }
+
+    pub fn known_good(&self) -> Option<String> {
+        read_lines(&self.server_dir.join(KNOWN_GOOD_NAME))
+            .into_iter()
+            .next()
+    }
+
+    fn mark_known_good(&self, version: &str) {
+        if self.known_good().as_deref() != Some(version) {
+            let _ = install::write_atomically(&self.server_dir.join(KNOWN_GOOD_NAME), version);
+        }
+    }
+
+    /// The version to switch to when `failing` keeps crashing: the last known-good version,
+    /// or else the newest other installed version that has not been rejected.
+    pub fn rollback_target(&self, failing: &str) -> Option<String> {
+        let installed = self.installed();
+        self.known_good()
+            .filter(|known_good| known_good != failing && installed.contains(known_good))
+            .or_else(|| {
+                installed
+                    .into_iter()
+                    .find(|version| version != failing && !self.is_rejected(version))
+            })
+    }
+
+    /// Stops `version` from being installed again by update checks. Its launch count is reset
+    /// so an explicit reinstall gets a fresh set of attempts.
+    pub fn reject(&self, version: &str) -> Result<()> {
+        let _ = fs::remove_file(self.version_dir(version).join(LAUNCH_ATTEMPTS_NAME));
+        let _ = fs::remove_dir_all(self.version_dir(version).join(LAUNCHES_DIR_NAME));
+        let path = self.server_dir.join(REJECTED_NAME);
+        let mut rejected = read_lines(&path);
+        if !rejected.iter().any(|rejected| rejected == version) {
+            rejected.push(version.to_owned());
+        }
+        install::write_atomically(&path, &rejected.join("\n"))
+    }
+
+    pub fn is_rejected(&self, version: &str) -> bool {
+        read_lines(&self.server_dir.join(REJECTED_NAME))
+            .iter()
+            .any(|rejected| rejected == version)
+    }
+
+    /// Deletes server assets that belong to neither `current_version` nor the retained
+    /// rollback set: other version directories, downloads made before versions had their own
+    /// directory (including other platforms' binaries), checksum files and interrupted
+    /// downloads. Bookkeeping files are left alone, and so is anything containing `in_use`.
+    /// Must only run while holding the install lock. Returns the removed paths.
+    pub fn remove_stale_assets(
+        &self,
+        current_version: Option<&str>,
+        in_use: &Path,
+    ) -> Vec<PathBuf> {
+        let mut retained: Vec<_> = self
+            .installed()
+            .into_iter()
+            .chain(self.known_good())
+            .chain(current_version.map(str::to_owned))
+            .map(|version| archive::version_dir_name(&version))
+            .collect();
+        retained.sort();
+        retained.dedup();
+
+        let Ok(entries) = fs::read_dir(&self.server_dir) else {
+            return Vec::new();
+        };
+        let mut removed = Vec::new();
+        for path in entries.flatten().map(|entry| entry.path()) {
+            let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+            let stale = if name.ends_with(STAGING_SUFFIX) {
+                true
+            } else if path.is_dir() {
+                name.starts_with(archive::VERSION_DIR_PREFIX)
+                    && !retained.iter().any(|dir| dir == name)
+            } else {
+                name.starts_with(SERVER_ASSET_PREFIX) || name == checksum::CHECKSUMS_ASSET_NAME
+            };
+            if !stale || in_use.starts_with(&path) {
+                continue;
+            }
+
+            let result = if path.is_dir() {
+                fs::remove_dir_all(&path)
+            } else {
+                fs::remove_file(&path)
+            };
+            if result.is_ok() {
+                removed.push(path);
+            }
+        }
+        removed.sort();
+        removed
+    }
+}
+
+/// Moves what extension builds before the managed cache left in `legacy_dir` (version
+/// directories, bookkeeping, the install record or `.release-version` marker, and old downloads)
+/// into `server_dir`, where they are reused, migrated and cleaned up like any other install.
+/// With `keep_server_assets`, top-level server executables stay put because they are the
+/// bundled server's. Entries `server_dir` already has are deleted rather than moved. Returns the
+/// new paths of the moved entries.
+pub fn migrate_legacy_dir(
+    legacy_dir: &Path,
+    server_dir: &Path,
+    keep_server_assets: bool,
+) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(legacy_dir) else {
+        return Vec::new();
+    };
+    if legacy_dir == server_dir || fs::create_dir_all(server_dir).is_err() {
+        return Vec::new();
+    }
+
+    let mut migrated = Vec::new();
+    for path in entries.flatten().map(|entry| entry.path()) {
+        let Some(name) = path.file_name() else {
+            continue;
+        };
+        let name_str = name.to_string_lossy();
+        if name_str == install::INSTALL_LOCK_NAME
+            || (keep_server_assets && path.is_file() && name_str.starts_with(SERVER_ASSET_PREFIX))
+        {
+            continue;
+        }
+        let target = server_dir.join(name);
+        if target.exists() {
+            let _ = if path.is_dir() {
+                fs::remove_dir_all(&path)
+            } else {
+                fs::remove_file(&path)
+            };
+        } else if fs::rename(&path, &target).is_ok() {
+            migrated.push(target);
+        }
+    }
+    migrated.sort();
+    migrated
+}
+
+fn read_lines(path: &Path) -> Vec<String> {
+    fs::read_to_string(path)
+        .unwrap_or_default()
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_owned)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{manifest, test_support::TempDir};
+
+    fn temp_store(name: &str) -> (TempDir, VersionStore) {
+        let dir = TempDir::new(&format!("versions-{name}"));
+        let store = VersionStore::new(&dir);
+        (dir, store)
+    }
+
+    fn install(store: &VersionStore, version: &str, keep: usize) {
+        fs::create_dir_all(store.version_dir(version)).unwrap();
+        fs::write(
+            server(store, version),
+            format!("process.env.{STARTED_MARKER_ENV}"),
+        )
+        .unwrap();
+        store.record_install(version, keep, None).unwrap();
+    }
+
+    fn server(store: &VersionStore, version: &str) -> PathBuf {
+        store.version_dir(version).join("wc-language-server.js")
+    }
+
+    fn at(secs: u64) -> Duration {
+        Duration::from_secs(1_700_000_000 + secs)
+    }
+
+    /// Launches `version` and has the server report that it started.
+    fn start(store: &VersionStore, version: &str, secs: u64) {
+        let launch = store.record_launch(version, &server(store, version), at(secs));
+        fs::write(launch.started_marker.unwrap(), "").unwrap();
+    }
+
+    #[test]
+    fn record_install_keeps_newest_versions_and_known_good() {
+        let (_dir, store) = temp_store("keep");
+        install(&store, "0.0.1", 2);
+        start(&store, "0.0.1", 0);
+        store.record_launch("0.0.1", &server(&store, "0.0.1"), at(1));
+        assert_eq!(store.known_good().as_deref(), Some("0.0.1"));
+
+        install(&store, "0.0.2", 2);
+        install(&store, "0.0.3", 2);
+        install(&store, "0.0.4", 2);
+
+        assert_eq!(store.installed(), ["0.0.4", "0.0.3", "0.0.1"]);
+        assert!(!store.version_dir("0.0.2").exists());
+        assert_eq!(
+            store.version_of(&store.version_dir("0.0.3").join("wc-language-server")),
+            Some("0.0.3".to_string())
+        );
+
+        fs::create_dir_all(store.version_dir("0.0.5")).unwrap();
+        store.record_install("0.0.5", 2, Some("0.0.3")).unwrap();
+        assert_eq!(store.installed(), ["0.0.5", "0.0.4", "0.0.3", "0.0.1"]);
+    }
+
+    #[test]
+    fn remove_stale_assets_keeps_current_rollback_set_and_bookkeeping() {
+        let (dir, store) = temp_store("gc");
+        install(&store, "0.0.1", 2);
+        install(&store, "0.0.2", 2);
+        install(&store, "0.0.3", 2);
+        // An old version directory that was never recorded in the history.
+        fs::create_dir_all(dir.join(archive::version_dir_name("0.0.0"))).unwrap();
+        let legacy_in_use = dir.join("wc-language-server-linux-x64");
+        for stale in [
+            "wc-language-server.js",
+            "wc-language-server-windows-x64.exe",
+            "SHA256SUMS",
+        ] {
+            fs::write(dir.join(stale), "").unwrap();
+        }
+        fs::write(&legacy_in_use, "").unwrap();
+        fs::create_dir_all(dir.join("release-wc-toolkit-language-server-0.0.4.download")).unwrap();
+        fs::write(dir.join(manifest::MANIFEST_NAME), "{}").unwrap();
+        fs::write(dir.join("notes.txt"), "").unwrap();
+
+        let removed = store.remove_stale_assets(Some("0.0.3"), &legacy_in_use);
+
+        let mut expected = vec![
+            dir.join(archive::version_dir_name("0.0.0")),
+            dir.join("release-wc-toolkit-language-server-0.0.4.download"),
+            dir.join("SHA256SUMS"),
+            dir.join("wc-language-server-windows-x64.exe"),
+            dir.join("wc-language-server.js"),
+        ];
+        expected.sort();
+        assert_eq!(removed, expected);
+        for kept in [
+            store.version_dir("0.0.2"),
+            store.version_dir("0.0.3"),
+            legacy_in_use,
+            dir.join(manifest::MANIFEST_NAME),
+            dir.join(".installed-versions"),
+            dir.join("notes.txt"),
+        ] {
+            assert!(kept.exists(), "{} should be kept", kept.display());
+        }
+    }
+
+    #[test]
+    fn migrate_legacy_dir_moves_old_installs_but_not_the_bundle() {
+        let (dir, store) = temp_store("migrate");
+        let legacy_dir = dir.join("legacy");
+        let server_dir = dir.join("managed");
+        let old_version = legacy_dir.join(archive::version_dir_name("0.0.7"));
+        fs::create_dir_all(&old_version).unwrap();
+        fs::write(old_version.join("wc-language-server-linux-x64"), "").unwrap();
+        fs::write(legacy_dir.join(".release-version"), "0.0.7").unwrap();
+        fs::write(legacy_dir.join("wc-language-server.js"), "").unwrap();
+        fs::write(legacy_dir.join(install::INSTALL_LOCK_NAME), "").unwrap();
+        fs::create_dir_all(&server_dir).unwrap();
+        fs::write(server_dir.join(HISTORY_NAME), "0.0.8").unwrap();
+        fs::write(legacy_dir.join(HISTORY_NAME), "0.0.7").unwrap();
+        drop(store);
+
+        let migrated = migrate_legacy_dir(&legacy_dir, &server_dir, true);
+
+        assert_eq!(
+            migrated,
+            [
+                server_dir.join(".release-version"),
+                server_dir.join(archive::version_dir_name("0.0.7")),
+            ]
+        );
+        assert!(legacy_dir.join("wc-language-server.js").exists());
+        assert!(!legacy_dir.join(HISTORY_NAME).exists());
+        assert_eq!(
+            fs::read_to_string(server_dir.join(HISTORY_NAME)).unwrap(),
+            "0.0.8"
+        );
+
+        // Without a bundle, old top-level downloads move too.
+        let migrated = migrate_legacy_dir(&legacy_dir, &server_dir, false);
+        assert_eq!(migrated, [server_dir.join("wc-language-server.js")]);
+        assert!(migrate_legacy_dir(&dir.join("missing"), &server_dir, false).is_empty());
+    }
+
+    #[test]
+    fn record_launch_counts_launches_that_never_write_their_own_marker() {
+        let (_dir, store) = temp_store("launch");
+        install(&store, "0.0.1", 3);
+        let executable = server(&store, "0.0.1");
+
+        // The same server starting for two worktrees at once gets a marker per launch.
+        let first = store.record_launch("0.0.1", &executable, at(0));
+        let second = store.record_launch("0.0.1", &executable, at(1));
+        assert_eq!((first.failed_launches, second.failed_launches), (0, 0));
+        assert_ne!(first.started_marker, second.started_marker);
+        fs::write(first.started_marker.unwrap(), "").unwrap();
+        fs::write(second.started_marker.unwrap(), "").unwrap();
+
+        assert_eq!(
+            store
+                .record_launch("0.0.1", &executable, at(2))
+                .failed_launches,
+            0
+        );
+        assert_eq!(store.known_good().as_deref(), Some("0.0.1"));
+        // The launch at 2s is still within its grace period...
+        assert_eq!(
+            store
+                .record_launch("0.0.1", &executable, at(60))
+                .failed_launches,
+            0
+        );
+        // ...and once it has passed, it and the launch at 60s count as failed.
+        assert_eq!(
+            store
+                .record_launch("0.0.1", &executable, at(300))
+                .failed_launches,
+            2
+        );
+    }
+
+    #[test]
+    fn record_launch_ignores_servers_without_started_markers() {
+        let (_dir, store) = temp_store("launch-legacy");
+        install(&store, "0.0.7", 3);
+        fs::write(server(&store, "0.0.7"), "console.log('0.0.2')").unwrap();
+
+        for secs in [0, 1_000, 2_000, 3_000] {
+            let launch = store.record_launch("0.0.7", &server(&store, "0.0.7"), at(secs));
+            assert!(launch.started_marker.is_none());
+            assert_eq!(launch.failed_launches, 0);
+        }
+    }
+
+    #[test]
+    fn rollback_prefers_known_good_then_unrejected_versions() {
+        let (_dir, store) = temp_store("rollback");
+        install(&store, "0.0.1", 3);
+        install(&store, "0.0.2", 3);
+        install(&store, "0.0.3", 3);
+
+        assert_eq!(store.rollback_target("0.0.3").as_deref(), Some("0.0.2"));
+        store.reject("0.0.2").unwrap();
+        assert!(store.is_rejected("0.0.2"));
+        assert_eq!(store.rollback_target("0.0.3").as_deref(), Some("0.0.1"));
+
+        start(&store, "0.0.2", 0);
+        store.record_launch("0.0.2", &server(&store, "0.0.2"), at(1));
+        assert_eq!(store.rollback_target("0.0.3").as_deref(), Some("0.0.2"));
+        assert_eq!(store.rollback_target("0.0.2").as_deref(), Some("0.0.3"));
+    }
+}
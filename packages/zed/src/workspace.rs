@@ -0,0 +1,433 @@
+use std::path::{Path, PathBuf};
+use zed_extension_api::{
+    self as zed,
+    serde_json::{self, Value, json},
+};
+
+use crate::project;
+
+const PNPM_WORKSPACE_FILE: &str = "pnpm-workspace.yaml";
+const PNPM_LOCKFILE: &str = "pnpm-lock.yaml";
+const NPM_LOCKFILE: &str = "package-lock.json";
+const MANIFEST_FILE_NAME: &str = "custom-elements.json";
+
+/// Where the web-components configuration lives in a worktree: the worktree itself and any
+/// workspace package with a `wc.config.*` or a custom-elements manifest. Paths are absolute.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ProjectRoots {
+    pub roots: Vec<PathBuf>,
+    pub config_paths: Vec<PathBuf>,
+    pub manifest_paths: Vec<PathBuf>,
+}
+
+impl ProjectRoots {
+    pub fn discover(worktree: &zed::Worktree) -> Self {
+        Self::discover_with(Path::new(&worktree.root_path()), |path| {
+            worktree.read_text_file(path).ok()
+        })
+    }
+
+    /// Checks the worktree root and every workspace package for config files and manifests,
+    /// reading worktree files through `read`.
+    fn discover_with(root: &Path, read: impl Fn(&str) -> Option<String>) -> Self {
+        let mut found = Self::default();
+        for package_dir in std::iter::once(String::new()).chain(workspace_packages(&read)) {
+            let in_package = |name: &str| {
+                if package_dir.is_empty() {
+                    name.to_owned()
+                } else {
+                    format!("{package_dir}/{name}")
+                }
+            };
+
+            let config = project::CONFIG_FILE_NAMES
+                .iter()
+                .map(|name| in_package(name))
+                .find(|path| read(path).is_some());
+            let mut manifests: Vec<String> = Vec::new();
+            if read(&in_package(MANIFEST_FILE_NAME)).is_some() {
+                manifests.push(in_package(MANIFEST_FILE_NAME));
+            }
+            if let Some(declared) = read(&in_package("package.json"))
+                .and_then(|contents| serde_json::from_str::<Value>(&contents).ok())
+                .and_then(|package_json| {
+                    package_json
+                        .get("customElements")?
+                        .as_str()
+                        .map(|path| in_package(path.trim_start_matches("./")))
+                })
+                && !manifests.contains(&declared)
+                && read(&declared).is_some()
+            {
+                manifests.push(declared);
+            }
+
+            if config.is_none() && manifests.is_empty() {
+                continue;
+            }
+            found.roots.push(if package_dir.is_empty() {
+                root.to_path_buf()
+            } else {
+                root.join(&package_dir)
+            });
+            found
+                .config_paths
+                .extend(config.map(|path| root.join(path)));
+            found
+                .manifest_paths
+                .extend(manifests.into_iter().map(|path| root.join(path)));
+        }
+        found
+    }
+
+    /// Why the worktree counts as a web-components project when only its workspace packages
+    /// say so, or `None` if nothing was found.
+    pub fn marker(&self) -> Option<String> {
+        self.config_paths
+            .first()
+            .or(self.manifest_paths.first())
+            .map(|path| format!("found {}", path.display()))
+    }
+
+    /// Adds `roots`, `configPaths` and `manifestPaths` to the user's initialization options,
+    /// keeping any of those keys the user set themselves.
+    pub fn merge_into(&self, options: Option<Value>) -> Value {
+        let paths = |paths: &[PathBuf]| -> Vec<String> {
+            paths
+                .iter()
+                .map(|path| path.to_string_lossy().into_owned())
+                .collect()
+        };
+        let mut options = match options {
+            Some(Value::Object(options)) => options,
+            _ => Default::default(),
+        };
+        for (key, value) in [
+            ("roots", json!(paths(&self.roots))),
+            ("configPaths", json!(paths(&self.config_paths))),
+            ("manifestPaths", json!(paths(&self.manifest_paths))),
+        ] {
+            options.entry(key).or_insert(value);
+        }
+        Value::Object(options)
+    }
+}
+
+/// Directories of the worktree's workspace packages, relative to the root. Extensions cannot
+/// list directories, so glob patterns from `pnpm-workspace.yaml` and `package.json`
+/// `workspaces` are matched against the package paths recorded in the lockfile; patterns
+/// without wildcards are used as they are.
+fn workspace_packages(read: &impl Fn(&str) -> Option<String>) -> Vec<String> {
+    let mut patterns = read(PNPM_WORKSPACE_FILE)
+        .map(|contents| pnpm_workspace_patterns(&contents))
+        .unwrap_or_default();
+    if let Some(package_json) =
+        read("package.json").and_then(|contents| serde_json::from_str::<Value>(&contents).ok())
+    {
+        patterns.extend(package_json_workspaces(&package_json));
+    }
+    let (excluded, included): (Vec<_>, Vec<_>) = patterns
+        .into_iter()
+        .map(|pattern| pattern.trim_end_matches('/').to_owned())
+        .partition(|pattern| pattern.starts_with('!'));
+    if included.is_empty() {
+        return Vec::new();
+    }
+
+    let mut candidates: Vec<String> = included
+        .iter()
+        .filter(|pattern| !pattern.contains('*'))
+        .map(|pattern| pattern.trim_start_matches("./").to_owned())
+        .collect();
+    if let Some(lockfile) = read(PNPM_LOCKFILE) {
+        candidates.extend(pnpm_importers(&lockfile));
+    }
+    if let Some(lockfile) =
+        read(NPM_LOCKFILE).and_then(|contents| serde_json::from_str::<Value>(&contents).ok())
+    {
+        candidates.extend(npm_lockfile_packages(&lockfile));
+    }
+
+    let mut packages: Vec<String> = candidates
+        .into_iter()
+        .filter(|dir| !dir.is_empty() && dir != ".")
+        .filter(|dir| included.iter().any(|pattern| glob_matches(pattern, dir)))
+        .filter(|dir| {
+            !excluded
+                .iter()
+                .any(|pattern| glob_matches(&pattern[1..], dir))
+        })
+        .collect();
+    packages.sort();
+    packages.dedup();
+    packages
+}
+
+/// The `packages` list of `pnpm-workspace.yaml`, in block (`- "packages/*"`) or flow
+/// (`["packages/*"]`) style.
+fn pnpm_workspace_patterns(contents: &str) -> Vec<String> {
+    let unquote = |value: &str| {
+        let value = match value.find(" #") {
+            Some(comment) => &value[..comment],
+            None => value,
+        };
+        value.trim().trim_matches(['"', '\'']).to_owned()
+    };
+
+    let mut lines = contents.lines();
+    let Some(inline) = lines
+        .by_ref()
+        .find_map(|line| line.strip_prefix("packages:"))
+    else {
+        return Vec::new();
+    };
+    if let Some(list) = inline.trim().strip_prefix('[') {
+        return list
+            .trim_end_matches(']')
+            .split(',')
+            .map(unquote)
+            .filter(|pattern| !pattern.is_empty())
+            .collect();
+    }
+
+    lines
+        .take_while(|line| line.trim().is_empty() || line.starts_with([' ', '\t', '-']))
+        .filter_map(|line| line.trim().strip_prefix('-'))
+        .map(unquote)
+        .filter(|pattern| !pattern.is_empty())
+        .collect()
+}
+
+/// `workspaces` in `package.json`: a list of patterns, or Yarn's `{ "packages": [...] }`.
+fn package_json_workspaces(package_json: &Value) -> Vec<String> {
+    let workspaces = match package_json.get("workspaces") {
+        Some(Value::Object(workspaces)) => workspaces.get("packages"),
+        workspaces => workspaces,
+    };
+    workspaces
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .map(str::to_owned)
+        .collect()
+}
+
+/// Package directories listed under `importers` in `pnpm-lock.yaml`.
+fn pnpm_importers(lockfile: &str) -> Vec<String> {
+    lockfile
+        .lines()
+        .skip_while(|line| !line.starts_with("importers:"))
+        .skip(1)
+        .take_while(|line| line.is_empty() || line.starts_with(' '))
+        .filter_map(|line| {
+            let key = line.strip_prefix("  ")?;
+            if key.starts_with(' ') {
+                return None;
+            }
+            let (key, _) = key.split_once(':')?;
+            Some(key.trim_matches(['"', '\'']).to_owned())
+        })
+        .collect()
+}
+
+/// Workspace package directories in an npm `package-lock.json` (v2 and later).
+fn npm_lockfile_packages(lockfile: &Value) -> Vec<String> {
+    lockfile
+        .get("packages")
+        .and_then(Value::as_object)
+        .into_iter()
+        .flat_map(|packages| packages.keys())
+        .filter(|path| !path.is_empty() && !path.split('/').any(|part| part == "node_modules"))
+        .cloned()
+        .collect()
+}
+
+/// Matches a relative directory against a workspace glob, where `*` matches within one path
+/// segment and `**` matches any number of segments.
+fn glob_matches(pattern: &str, path: &str) -> bool {
+    let pattern: Vec<&str> = pattern
+        .trim_start_matches("./")
+        .split('/')
+        .filter(|part| !part.is_empty())
+        .collect();
+    let path: Vec<&str> = path.split('/').filter(|part| !part.is_empty()).collect();
+    segments_match(&pattern, &path)
+}
+
+fn segments_match(pattern: &[&str], path: &[&str]) -> bool {
+    match (pattern.first(), path.first()) {
+        (None, None) => true,
+        (Some(&"**"), _) => {
+            segments_match(&pattern[1..], path)
+                || (!path.is_empty() && segments_match(pattern, &path[1..]))
+        }
+        (Some(segment), Some(part)) => {
+            segment_matches(segment, part) && segments_match(&pattern[1..], &path[1..])
+        }
+        _ => false,
+    }
+}
+
+fn segment_matches(pattern: &str, part: &str) -> bool {
+    let Some((prefix, rest)) = pattern.split_once('*') else {
+        return pattern == part;
+    };
+    let Some(part) = part.strip_prefix(prefix) else {
+        return false;
+    };
+    if rest.is_empty() {
+        return true;
+    }
+    (0..=part.len())
+        .filter(|index| part.is_char_boundary(*index))
+        .any(|index| segment_matches(rest, &part[index..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn discover(files: &[(&str, &str)]) -> ProjectRoots {
+        let files: HashMap<_, _> = files.iter().copied().collect();
+        ProjectRoots::discover_with(Path::new("/repo"), |path| {
+            files.get(path).map(|contents| contents.to_string())
+        })
+    }
+
+    #[test]
+    fn discover_finds_configs_in_pnpm_workspace_packages() {
+        let roots = discover(&[
+            (
+                "pnpm-workspace.yaml",
+                "packages:\n  - \"packages/*\"\n  - 'apps/**' # apps\n  - \"!**/fixtures/**\"\n",
+            ),
+            (
+                "pnpm-lock.yaml",
+                "lockfileVersion: '9.0'\n\nimporters:\n\n  .:\n    devDependencies: {}\n\n  packages/design-system:\n    dependencies:\n      lit:\n        specifier: ^3.0.0\n\n  packages/fixtures/broken: {}\n\n  apps/docs/site: {}\n\npackages:\n\n  lit@3.0.0:\n    resolution: {}\n",
+            ),
+            ("packages/design-system/wc.config.js", "export default {}"),
+            (
+                "packages/design-system/package.json",
+                r#"{ "customElements": "./dist/custom-elements.json" }"#,
+            ),
+            ("packages/design-system/dist/custom-elements.json", "{}"),
+            ("packages/fixtures/broken/wc.config.js", "export default {}"),
+            ("apps/docs/site/custom-elements.json", "{}"),
+        ]);
+
+        assert_eq!(
+            roots,
+            ProjectRoots {
+                roots: vec![
+                    PathBuf::from("/repo/apps/docs/site"),
+                    PathBuf::from("/repo/packages/design-system"),
+                ],
+                config_paths: vec![PathBuf::from("/repo/packages/design-system/wc.config.js")],
+                manifest_paths: vec![
+                    PathBuf::from("/repo/apps/docs/site/custom-elements.json"),
+                    PathBuf::from("/repo/packages/design-system/dist/custom-elements.json"),
+                ],
+            }
+        );
+    }
+
+    #[test]
+    fn marker_names_a_workspace_package_config_or_manifest() {
+        let files = [
+            ("package.json", r#"{ "workspaces": ["packages/*"] }"#),
+            (
+                "package-lock.json",
+                r#"{ "packages": { "": {}, "packages/ui": {} } }"#,
+            ),
+        ];
+        assert_eq!(discover(&files).marker(), None);
+
+        let mut with_manifest = files.to_vec();
+        with_manifest.push(("packages/ui/custom-elements.json", "{}"));
+        assert_eq!(
+            discover(&with_manifest).marker().as_deref(),
+            Some("found /repo/packages/ui/custom-elements.json")
+        );
+    }
+
+    #[test]
+    fn discover_reads_package_json_workspaces_and_npm_lockfile() {
+        let roots = discover(&[
+            ("wc.config.json", "{}"),
+            (
+                "package.json",
+                r#"{ "workspaces": { "packages": ["components/*", "docs"] } }"#,
+            ),
+            (
+                "package-lock.json",
+                r#"{ "packages": { "": {}, "components/button": {}, "node_modules/lit": {}, "components/button/node_modules/x": {} } }"#,
+            ),
+            ("components/button/custom-elements.json", "{}"),
+            ("docs/wc.config.mjs", "export default {}"),
+        ]);
+
+        assert_eq!(
+            roots.roots,
+            [
+                PathBuf::from("/repo"),
+                PathBuf::from("/repo/components/button"),
+                PathBuf::from("/repo/docs"),
+            ]
+        );
+        assert_eq!(
+            roots.config_paths,
+            [
+                PathBuf::from("/repo/wc.config.json"),
+                PathBuf::from("/repo/docs/wc.config.mjs"),
+            ]
+        );
+    }
+
+    #[test]
+    fn pnpm_workspace_patterns_support_flow_style() {
+        assert_eq!(
+            pnpm_workspace_patterns("packages: ['packages/*', \"tools\"]\n"),
+            ["packages/*", "tools"]
+        );
+        assert!(pnpm_workspace_patterns("catalog:\n  lit: ^3.0.0\n").is_empty());
+    }
+
+    #[test]
+    fn glob_matches_single_and_recursive_wildcards() {
+        assert!(glob_matches("packages/*", "packages/design-system"));
+        assert!(!glob_matches("packages/*", "packages/a/b"));
+        assert!(glob_matches("packages/**", "packages/a/b"));
+        assert!(glob_matches("**/fixtures/**", "packages/fixtures/broken"));
+        assert!(glob_matches("./apps/web-*", "apps/web-docs"));
+        assert!(!glob_matches("apps/web-*", "apps/docs"));
+    }
+
+    #[test]
+    fn merge_into_keeps_user_options() {
+        let roots = ProjectRoots {
+            roots: vec![PathBuf::from("/repo")],
+            config_paths: vec![PathBuf::from("/repo/wc.config.js")],
+            manifest_paths: Vec::new(),
+        };
+
+        assert_eq!(
+            roots.merge_into(Some(json!({ "roots": ["/custom"], "tsdk": "/ts" }))),
+            json!({
+                "roots": ["/custom"],
+                "tsdk": "/ts",
+                "configPaths": ["/repo/wc.config.js"],
+                "manifestPaths": []
+            })
+        );
+        assert_eq!(
+            roots.merge_into(None),
+            json!({
+                "roots": ["/repo"],
+                "configPaths": ["/repo/wc.config.js"],
+                "manifestPaths": []
+            })
+        );
+    }
+}